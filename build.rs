@@ -37,4 +37,13 @@ fn main() {
             }
         }
     }
+
+    // python3-sys also exports the minor version of Python 3, so that the same
+    // #[cfg(Py_3_x)] blocks can be used as in python3-sys.
+    if let Ok(minor) = env::var("DEP_PYTHON3_PYTHON_MINOR_VERSION") {
+        let minor: u32 = minor.parse().unwrap();
+        for i in 4..(minor+1) {
+            println!("cargo:rustc-cfg=Py_3_{}", i);
+        }
+    }
 }
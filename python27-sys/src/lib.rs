@@ -125,6 +125,7 @@ mod eval;
 // Additional headers that are not exported by Python.h
 pub mod structmember;
 pub mod frameobject;
+pub mod marshal;
 
 pub const Py_single_input: libc::c_int = 256;
 pub const Py_file_input: libc::c_int = 257;
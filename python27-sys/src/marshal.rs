@@ -0,0 +1,12 @@
+use libc::{c_char, c_int};
+use pyport::Py_ssize_t;
+use object::PyObject;
+
+// marshal.h is not included by Python.h
+
+pub const Py_MARSHAL_VERSION: c_int = 2;
+
+#[cfg_attr(windows, link(name="pythonXY"))] extern "C" {
+    pub fn PyMarshal_WriteObjectToString(object: *mut PyObject, version: c_int) -> *mut PyObject;
+    pub fn PyMarshal_ReadObjectFromString(data: *mut c_char, len: Py_ssize_t) -> *mut PyObject;
+}
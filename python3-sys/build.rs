@@ -216,6 +216,12 @@ fn get_rustc_link_lib(_: &PythonVersion, ld_version: &str, _: bool) -> Result<St
     }
 }
 
+/// Whether we are building for Emscripten (e.g. for Pyodide).
+/// `cfg!(target_os)` can't be used here: it describes the host running the build script.
+fn is_emscripten_target() -> bool {
+    env::var("CARGO_CFG_TARGET_OS").map(|os| os == "emscripten").unwrap_or(false)
+}
+
 /// Parse string as interpreter version.
 fn get_interpreter_version(line: &str) -> Result<PythonVersion, String> {
     let version_re = Regex::new(r"\((\d+), (\d+)\)").unwrap();
@@ -316,7 +322,9 @@ fn configure_from_path(expected_version: &PythonVersion) -> Result<String, Strin
     let exec_prefix: &str = &lines[3];
 
     let is_extension_module = env::var_os("CARGO_FEATURE_EXTENSION_MODULE").is_some();
-    if !is_extension_module || cfg!(target_os="windows") {
+    // Emscripten extension modules are loaded as side modules, their Python symbols are
+    // resolved against the interpreter's main module: there is no libpython to link.
+    if !is_emscripten_target() && (!is_extension_module || cfg!(target_os="windows")) {
         println!("{}", get_rustc_link_lib(&interpreter_version,
             ld_version, enable_shared == "1").unwrap());
         if libpath != "None" {
@@ -335,6 +343,8 @@ fn configure_from_path(expected_version: &PythonVersion) -> Result<String, Strin
             for i in 4..(minor+1) {
                 println!("cargo:rustc-cfg=Py_3_{}", i);
             }
+            // rust-cpython/build.rs turns this into the same Py_3_x cfg flags.
+            println!("cargo:python_minor_version={}", minor);
         }
     }
 
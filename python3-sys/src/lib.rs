@@ -162,6 +162,9 @@ mod fileutils;
 // Additional headers that are not exported by Python.h
 pub mod structmember; // TODO supports PEP-384 only; needs adjustment for Python 3.3 and 3.5
 
+#[cfg(not(Py_LIMITED_API))]
+pub mod marshal;
+
 #[cfg(not(Py_LIMITED_API))]
 pub mod frameobject;
 #[cfg(Py_LIMITED_API)]
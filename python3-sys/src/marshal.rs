@@ -0,0 +1,15 @@
+use libc::{c_char, c_int};
+use pyport::Py_ssize_t;
+use object::PyObject;
+
+// marshal.h is not included by Python.h, and excluded by PEP-384
+
+#[cfg(Py_3_4)]
+pub const Py_MARSHAL_VERSION: c_int = 4;
+#[cfg(not(Py_3_4))]
+pub const Py_MARSHAL_VERSION: c_int = 2;
+
+#[cfg_attr(windows, link(name="pythonXY"))] extern "C" {
+    pub fn PyMarshal_WriteObjectToString(object: *mut PyObject, version: c_int) -> *mut PyObject;
+    pub fn PyMarshal_ReadObjectFromString(data: *const c_char, len: Py_ssize_t) -> *mut PyObject;
+}
@@ -47,6 +47,8 @@ pub enum PyGILState_STATE {
     pub fn PyGILState_Ensure() -> PyGILState_STATE;
     pub fn PyGILState_Release(arg1: PyGILState_STATE) -> ();
     pub fn PyGILState_GetThisThreadState() -> *mut PyThreadState;
+    #[cfg(Py_3_4)]
+    pub fn PyGILState_Check() -> libc::c_int;
 }
 
 #[inline(always)]
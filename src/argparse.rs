@@ -266,9 +266,12 @@ macro_rules! py_argparse_parse_plist_impl {
             ($($tail)*)
         }
     };
+    // Optional parameters: a default value made of a single token (e.g. a literal),
+    // or a negated token, is kept as is so that `py_text_signature!()` can render it;
+    // any other default value is parenthesized.
     // Optional parameter with reference extraction
     { $callback:ident $initial_args:tt [ $($output:tt)* ]
-        ( $name:ident : &$t:ty = $default:expr, $($tail:tt)* )
+        ( $name:ident : &$t:ty = $default:tt , $($tail:tt)* )
     } => {
         py_argparse_parse_plist_impl! {
             $callback $initial_args
@@ -276,9 +279,27 @@ macro_rules! py_argparse_parse_plist_impl {
             ($($tail)*)
         }
     };
+    { $callback:ident $initial_args:tt [ $($output:tt)* ]
+        ( $name:ident : &$t:ty = - $default:tt , $($tail:tt)* )
+    } => {
+        py_argparse_parse_plist_impl! {
+            $callback $initial_args
+            [ $($output)* { $name:&$t = [ {} {- $default} {$t} ] } ]
+            ($($tail)*)
+        }
+    };
+    { $callback:ident $initial_args:tt [ $($output:tt)* ]
+        ( $name:ident : &$t:ty = $default:expr, $($tail:tt)* )
+    } => {
+        py_argparse_parse_plist_impl! {
+            $callback $initial_args
+            [ $($output)* { $name:&$t = [ {} {($default)} {$t} ] } ]
+            ($($tail)*)
+        }
+    };
     // Optional parameter
     { $callback:ident $initial_args:tt [ $($output:tt)* ]
-        ( $name:ident : $t:ty = $default:expr , $($tail:tt)* )
+        ( $name:ident : $t:ty = $default:tt , $($tail:tt)* )
     } => {
         py_argparse_parse_plist_impl! {
             $callback $initial_args
@@ -286,6 +307,24 @@ macro_rules! py_argparse_parse_plist_impl {
             ($($tail)*)
         }
     };
+    { $callback:ident $initial_args:tt [ $($output:tt)* ]
+        ( $name:ident : $t:ty = - $default:tt , $($tail:tt)* )
+    } => {
+        py_argparse_parse_plist_impl! {
+            $callback $initial_args
+            [ $($output)* { $name:$t = [ {} {- $default} {} ] } ]
+            ($($tail)*)
+        }
+    };
+    { $callback:ident $initial_args:tt [ $($output:tt)* ]
+        ( $name:ident : $t:ty = $default:expr , $($tail:tt)* )
+    } => {
+        py_argparse_parse_plist_impl! {
+            $callback $initial_args
+            [ $($output)* { $name:$t = [ {} {($default)} {} ] } ]
+            ($($tail)*)
+        }
+    };
 }
 
 // The main py_argparse!() macro, except that it expects the parameter-list
@@ -421,6 +421,25 @@ impl PyBuffer {
         self.to_vec_impl(py, b'F')
     }
 
+    /// Copies the raw bytes of the buffer to a newly allocated vector, whatever the format
+    /// of the elements, like `bytes(memoryview(obj))` in Python.
+    /// If the buffer is multi-dimensional or not contiguous, the bytes are written in
+    /// C-style order.
+    pub fn to_bytes(&self, py: Python) -> PyResult<Vec<u8>> {
+        let len = self.len_bytes();
+        let mut vec: Vec<u8> = Vec::with_capacity(len);
+        unsafe {
+            err::error_on_minusone(py, ffi::PyBuffer_ToContiguous(
+                vec.as_mut_ptr() as *mut libc::c_void,
+                &*self.0 as *const ffi::Py_buffer as *mut ffi::Py_buffer,
+                self.0.len,
+                b'C' as libc::c_char
+            ))?;
+            vec.set_len(len);
+        }
+        Ok(vec)
+    }
+
     fn to_vec_impl<T: Element+Copy>(&self, py: Python, fort: u8) -> PyResult<Vec<T>> {
         if !T::is_compatible_format(self.format()) || mem::size_of::<T>() != self.item_size() {
             incompatible_format_error(py)?;
@@ -0,0 +1,157 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Interior mutability for the data of `py_class!` instances.
+
+use std::cell::{Ref, RefCell, RefMut};
+use err::{PyErr, PyResult};
+use exc;
+use python::Python;
+
+/// A `RefCell` for the `data` members of a `py_class!`, reporting borrow conflicts as
+/// Python exceptions.
+///
+/// Python code can call back into an object while one of its methods is running,
+/// e.g. from an `__eq__` method or a callback invoked with the data still borrowed.
+/// With a plain `RefCell`, a conflicting borrow by the reentrant call panics;
+/// with `PyRefCell`, it raises `RuntimeError`, which propagates through the Python code
+/// like any other exception.
+///
+/// The checks are opt-in: `py_class!` doesn't change the behavior of `RefCell` members,
+/// so declare the member as a `PyRefCell` to get them.
+///
+/// ```
+/// #[macro_use] extern crate cpython;
+/// use cpython::{Python, PyObject, PyRefCell, PyResult, ObjectProtocol};
+///
+/// py_class!(class Counter |py| {
+///     data count: PyRefCell<u64>;
+///
+///     def increment(&self) -> PyResult<u64> {
+///         let mut count = self.count(py).borrow_mut(py)?;
+///         *count += 1;
+///         Ok(*count)
+///     }
+///
+///     def increment_with(&self, callback: PyObject) -> PyResult<u64> {
+///         let mut count = self.count(py).borrow_mut(py)?;
+///         // `callback` may call `increment()`, which raises `RuntimeError`.
+///         *count += callback.call(py, cpython::NoArgs, None)?.extract::<u64>(py)?;
+///         Ok(*count)
+///     }
+/// });
+/// # fn main() {}
+/// ```
+#[derive(Debug, Default)]
+pub struct PyRefCell<T> {
+    inner: RefCell<T>,
+}
+
+impl <T> PyRefCell<T> {
+    /// Creates a new `PyRefCell` containing `value`.
+    pub fn new(value: T) -> PyRefCell<T> {
+        PyRefCell { inner: RefCell::new(value) }
+    }
+
+    /// Immutably borrows the value.
+    ///
+    /// Raises `RuntimeError` if the value is currently mutably borrowed.
+    pub fn borrow<'a>(&'a self, py: Python<'a>) -> PyResult<Ref<'a, T>> {
+        match self.inner.try_borrow() {
+            Ok(value) => Ok(value),
+            Err(_) => Err(PyErr::new::<exc::RuntimeError, _>(py, "Already mutably borrowed")),
+        }
+    }
+
+    /// Mutably borrows the value.
+    ///
+    /// Raises `RuntimeError` if the value is currently borrowed.
+    pub fn borrow_mut<'a>(&'a self, py: Python<'a>) -> PyResult<RefMut<'a, T>> {
+        match self.inner.try_borrow_mut() {
+            Ok(value) => Ok(value),
+            Err(_) => Err(PyErr::new::<exc::RuntimeError, _>(py, "Already borrowed")),
+        }
+    }
+
+    /// Replaces the value, returning the old one.
+    ///
+    /// Raises `RuntimeError` if the value is currently borrowed.
+    pub fn replace(&self, py: Python, value: T) -> PyResult<T> {
+        Ok(::std::mem::replace(&mut *self.borrow_mut(py)?, value))
+    }
+
+    /// Gets the value, which can't be borrowed since `self` is borrowed mutably.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+
+    /// Unwraps the value.
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use python::Python;
+    use objects::{PyDict, PyObject};
+    use objectprotocol::ObjectProtocol;
+    use err::PyResult;
+    use exc;
+    use super::PyRefCell;
+
+    py_class!(class Items |py| {
+        data items: PyRefCell<Vec<PyObject>>;
+
+        def push(&self, item: PyObject) -> PyResult<PyObject> {
+            self.items(py).borrow_mut(py)?.push(item);
+            Ok(py.None())
+        }
+
+        def count(&self, item: PyObject) -> PyResult<usize> {
+            // Comparing the items may call back into `push()`.
+            let mut count = 0;
+            for i in self.items(py).borrow(py)?.iter() {
+                if i.rich_compare(py, &item, ::CompareOp::Eq)?.is_true(py)? {
+                    count += 1;
+                }
+            }
+            Ok(count)
+        }
+    });
+
+    #[test]
+    fn test_reentrant_borrow() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let items = Items::create_instance(py, PyRefCell::new(Vec::new())).unwrap();
+        let d = PyDict::new(py);
+        d.set_item(py, "__builtins__", py.builtins().unwrap()).unwrap();
+        d.set_item(py, "items", &items).unwrap();
+        py.run("class Reentrant:\n\
+                \x20   def __eq__(self, other):\n\
+                \x20       items.push(other)\n\
+                \x20       return True\n\
+                items.push(1)\n\
+                assert items.count(1) == 1",
+            Some(&d), None).unwrap();
+        let err = py.run("items.count(Reentrant())", Some(&d), None).unwrap_err();
+        assert!(err.matches(py, py.get_type::<exc::RuntimeError>()));
+        assert_eq!(items.items(py).borrow(py).unwrap().len(), 1);
+    }
+}
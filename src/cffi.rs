@@ -0,0 +1,159 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Interoperability with [cffi](https://cffi.readthedocs.io/).
+//!
+//! These functions give access to the memory of cffi `cdata` objects without copies,
+//! so that Rust extensions can work on buffers allocated by cffi-based libraries.
+//! They use the `_cffi_backend` module directly, so they work with the cdata
+//! of any `cffi.FFI` instance.
+//!
+//! A pointer obtained from a cdata object is only valid as long as the memory it points to
+//! is alive; for memory allocated by `ffi.new()`, that is as long as the owning cdata object.
+//!
+//! ```
+//! use cpython::{Python, cffi};
+//!
+//! let gil = Python::acquire_gil();
+//! let py = gil.python();
+//! # if py.import("cffi").is_err() { return; }
+//! let cdata = py.eval("__import__('cffi').FFI().new('uint16_t[3]', [1, 2, 3])", None, None).unwrap();
+//! let data: &[u16] = unsafe { cffi::as_slice(py, &cdata) }.unwrap();
+//! assert_eq!(data, &[1, 2, 3]);
+//! ```
+
+use std::{mem, slice};
+use libc::c_void;
+use buffer::PyBuffer;
+use err::{PyErr, PyResult};
+use exc;
+use objects::{PyObject, PyModule, PyType};
+use objectprotocol::ObjectProtocol;
+use python::Python;
+
+fn backend(py: Python) -> PyResult<PyModule> {
+    py.import("_cffi_backend")
+}
+
+/// Gets whether `obj` is a cffi `cdata` object.
+///
+/// Raises `ImportError` if cffi is not installed.
+pub fn is_cdata(py: Python, obj: &PyObject) -> PyResult<bool> {
+    let cdata_type = backend(py)?.get(py, "_CDataBase")?.cast_into::<PyType>(py)?;
+    Ok(cdata_type.is_instance(py, obj))
+}
+
+/// Gets the address of a `cdata` pointer or array.
+///
+/// Raises `TypeError` if `obj` is not a cdata object that can be cast to a pointer.
+pub fn as_ptr(py: Python, obj: &PyObject) -> PyResult<*mut c_void> {
+    let backend = backend(py)?;
+    let uintptr_t = backend.call(py, "new_primitive_type", ("uintptr_t",), None)?;
+    let address = backend.call(py, "cast", (uintptr_t, obj), None)?;
+    Ok(address.extract::<usize>(py)? as *mut c_void)
+}
+
+/// Gets the size of a `cdata` object, in bytes (`ffi.sizeof(obj)`).
+pub fn size_of(py: Python, obj: &PyObject) -> PyResult<usize> {
+    backend(py)?.call(py, "sizeof", (obj,), None)?.extract(py)
+}
+
+/// Gets a buffer over the memory of a `cdata` object (`ffi.buffer(obj, size)`).
+///
+/// If `size` is `None`, the size of an array, or of the object pointed to by a pointer
+/// is used.
+pub fn buffer(py: Python, obj: &PyObject, size: Option<usize>) -> PyResult<PyBuffer> {
+    let backend = backend(py)?;
+    let buffer = match size {
+        Some(size) => backend.call(py, "buffer", (obj, size), None)?,
+        None => backend.call(py, "buffer", (obj,), None)?,
+    };
+    PyBuffer::get(py, &buffer)
+}
+
+/// Gets the content of a `cdata` array as a slice.
+///
+/// Raises `TypeError` if `obj` is not an array, or if the size of the array is not
+/// a multiple of the size of `T`.
+///
+/// # Safety
+/// The caller must ensure that `T` is appropriate for the array's item type,
+/// and that the array is not modified while the slice is in use.
+pub unsafe fn as_slice<'a, T>(py: Python, obj: &'a PyObject) -> PyResult<&'a [T]> {
+    let backend = backend(py)?;
+    let kind: String = backend.call(py, "typeof", (obj,), None)?.getattr(py, "kind")?.extract(py)?;
+    if kind != "array" {
+        return Err(PyErr::new::<exc::TypeError, _>(py, "expected a cdata array"));
+    }
+    let size = size_of(py, obj)?;
+    if mem::size_of::<T>() == 0 || size % mem::size_of::<T>() != 0 {
+        return Err(PyErr::new::<exc::TypeError, _>(py, "cdata array size is not a multiple of the item size"));
+    }
+    let len = size / mem::size_of::<T>();
+    if len == 0 {
+        return Ok(&[]);
+    }
+    Ok(slice::from_raw_parts(as_ptr(py, obj)? as *const T, len))
+}
+
+/// Wraps `ptr` as a `cdata` pointer of type `ctype` (`ffi.cast(ctype, ptr)`).
+///
+/// `ctype` is a cffi type object, e.g. the result of `ffi.typeof("int *")`.
+///
+/// # Safety
+/// The memory pointed to by `ptr` must be valid for `ctype`, and must stay valid
+/// as long as the returned object is used.
+pub unsafe fn cast(py: Python, ctype: &PyObject, ptr: *mut c_void) -> PyResult<PyObject> {
+    backend(py)?.call(py, "cast", (ctype, ptr as usize), None)
+}
+
+#[cfg(test)]
+mod test {
+    use python::Python;
+    use objects::PyDict;
+    use objectprotocol::ObjectProtocol;
+    use super::*;
+
+    #[test]
+    fn test_cdata_array() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        if py.import("cffi").is_err() {
+            return;
+        }
+        let d = PyDict::new(py);
+        py.run("import cffi\nffi = cffi.FFI()\narr = ffi.new('int32_t[4]', [1, 2, 3, 4])",
+            None, Some(&d)).unwrap();
+        let arr = d.get_item(py, "arr").unwrap();
+        assert!(is_cdata(py, &arr).unwrap());
+        assert!(!is_cdata(py, &py.None()).unwrap());
+        assert_eq!(size_of(py, &arr).unwrap(), 16);
+        assert_eq!(unsafe { as_slice::<i32>(py, &arr) }.unwrap(), &[1, 2, 3, 4]);
+        assert!(unsafe { as_slice::<[u8; 3]>(py, &arr) }.is_err());
+        let buf = buffer(py, &arr, None).unwrap();
+        assert_eq!(buf.len_bytes(), 16);
+        assert_eq!(buf.buf_ptr(), as_ptr(py, &arr).unwrap());
+
+        // wrap the Rust pointer back into a cdata object
+        let ctype = py.eval("ffi.typeof('int32_t *')", None, Some(&d)).unwrap();
+        let p = unsafe { cast(py, &ctype, as_ptr(py, &arr).unwrap()) }.unwrap();
+        assert_eq!(p.get_item(py, 2).unwrap().extract::<i32>(py).unwrap(), 3);
+        assert!(unsafe { as_slice::<i32>(py, &p) }.is_err());
+        assert_eq!(buffer(py, &p, Some(8)).unwrap().len_bytes(), 8);
+    }
+}
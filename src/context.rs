@@ -0,0 +1,173 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Support for using Python context managers from Rust.
+
+use std::thread;
+use ffi;
+use err::{PyErr, PyResult};
+use exc;
+use objects::PyObject;
+use objectprotocol::ObjectProtocol;
+use python::{Python, PyClone};
+use pythonrun::GILGuard;
+
+/// Guard for the runtime context of a Python context manager; returned by
+/// `ObjectProtocol::enter_context()`.
+///
+/// Dropping the guard calls `__exit__(None, None, None)` (or, if the thread is panicking,
+/// `__exit__` with a `RuntimeError` so that e.g. a transaction is rolled back rather than
+/// committed). Errors raised by `__exit__` while dropping the guard are reported with
+/// `sys.unraisablehook`; use `exit()` or `exit_with_error()` to handle them.
+///
+/// ```
+/// use cpython::{Python, ObjectProtocol, NoArgs};
+///
+/// let gil = Python::acquire_gil();
+/// let py = gil.python();
+/// let lock = py.import("threading").unwrap().call(py, "Lock", NoArgs, None).unwrap();
+/// {
+///     let _guard = lock.enter_context(py).unwrap();
+///     assert!(lock.call_method(py, "locked", NoArgs, None).unwrap().is_true(py).unwrap());
+/// }
+/// assert!(!lock.call_method(py, "locked", NoArgs, None).unwrap().is_true(py).unwrap());
+/// ```
+#[must_use]
+pub struct ContextGuard {
+    manager: PyObject,
+    value: PyObject,
+    // `None` once `__exit__` was called.
+    exit: Option<PyObject>,
+}
+
+impl ContextGuard {
+    /// Calls `manager.__enter__()`; same as `manager.enter_context(py)`.
+    pub fn enter(py: Python, manager: &PyObject) -> PyResult<ContextGuard> {
+        // Like the `with` statement, look up `__exit__` before calling `__enter__`.
+        let exit = manager.getattr(py, "__exit__")?;
+        let value = manager.call_method(py, "__enter__", ::NoArgs, None)?;
+        Ok(ContextGuard { manager: manager.clone_ref(py), value, exit: Some(exit) })
+    }
+
+    /// Gets the value returned by `__enter__()` (the target of `as` in a `with` statement).
+    pub fn value(&self) -> &PyObject {
+        &self.value
+    }
+
+    /// Calls `__exit__(None, None, None)`.
+    pub fn exit(mut self, py: Python) -> PyResult<()> {
+        let exit = self.exit.take().unwrap();
+        exit.call(py, (py.None(), py.None(), py.None()), None)?;
+        Ok(())
+    }
+
+    /// Calls `__exit__()` with the exception `err`, raised from the body of the context.
+    ///
+    /// Returns `Ok(())` if the context manager suppressed the exception,
+    /// or `Err(err)` otherwise (or the exception raised by `__exit__()`).
+    pub fn exit_with_error(mut self, py: Python, mut err: PyErr) -> PyResult<()> {
+        let exit = self.exit.take().unwrap();
+        let value = err.instance(py);
+        let traceback = match err.ptraceback {
+            Some(ref traceback) => traceback.clone_ref(py),
+            None => py.None(),
+        };
+        let ty = err.get_type(py);
+        if exit.call(py, (ty, value, traceback), None)?.is_true(py)? {
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let exit = match self.exit.take() {
+            Some(exit) => exit,
+            None => return,
+        };
+        let gil = GILGuard::acquire();
+        let py = gil.python();
+        let result = if thread::panicking() {
+            let mut err = PyErr::new::<exc::RuntimeError, _>(py, "Rust panic");
+            let value = err.instance(py);
+            exit.call(py, (err.get_type(py), value, py.None()), None)
+        } else {
+            exit.call(py, (py.None(), py.None(), py.None()), None)
+        };
+        if let Err(e) = result {
+            e.restore(py);
+            unsafe { ffi::PyErr_WriteUnraisable(self.manager.as_ptr()) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::panic;
+    use python::Python;
+    use objects::{PyDict, PyObject};
+    use objectprotocol::ObjectProtocol;
+    use err::PyErr;
+    use exc;
+
+    fn manager(py: Python) -> (PyDict, PyObject) {
+        let d = PyDict::new(py);
+        py.run("class Manager:\n\
+                \x20   exits = []\n\
+                \x20   def __enter__(self): return 'value'\n\
+                \x20   def __exit__(self, ty, value, tb):\n\
+                \x20       self.exits.append(ty)\n\
+                \x20       return ty is KeyError\n\
+                m = Manager()",
+            None, Some(&d)).unwrap();
+        let m = d.get_item(py, "m").unwrap();
+        (d, m)
+    }
+
+    #[test]
+    fn test_enter_context() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let (d, m) = manager(py);
+        {
+            let guard = m.enter_context(py).unwrap();
+            assert_eq!(guard.value().extract::<String>(py).unwrap(), "value");
+        }
+        m.enter_context(py).unwrap().exit(py).unwrap();
+        let err = PyErr::new::<exc::KeyError, _>(py, "key");
+        assert!(m.enter_context(py).unwrap().exit_with_error(py, err).is_ok());
+        let err = PyErr::new::<exc::ValueError, _>(py, "value");
+        assert!(m.enter_context(py).unwrap().exit_with_error(py, err).is_err());
+        py.run("assert m.exits == [None, None, KeyError, ValueError]", None, Some(&d)).unwrap();
+    }
+
+    #[test]
+    fn test_exit_on_panic() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let (d, m) = manager(py);
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let _guard = m.enter_context(py).unwrap();
+            panic!("error in context");
+        }));
+        assert!(result.is_err());
+        py.run("assert m.exits == [RuntimeError]", None, Some(&d)).unwrap();
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Interoperability with the `ctypes` module.
+//!
+//! These functions convert between Rust pointers and ctypes objects, so that Rust code can
+//! be called with the pointers that Python code already manipulates through ctypes, and
+//! vice versa.
+//!
+//! A pointer obtained from a ctypes object is only valid as long as that object is alive
+//! (and, for a `CFUNCTYPE` callback, as long as the callback object is alive).
+//!
+//! ```
+//! use cpython::{Python, ctypes};
+//!
+//! let gil = Python::acquire_gil();
+//! let py = gil.python();
+//! let array = py.eval("(__import__('ctypes').c_uint8 * 4)(1, 2, 3, 4)", None, None).unwrap();
+//! let (ptr, len) = ctypes::buffer(py, &array).unwrap();
+//! let data = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) };
+//! assert_eq!(data, &[1, 2, 3, 4]);
+//! ```
+
+use std::ptr;
+use libc::c_void;
+use err::PyResult;
+use objects::{PyObject, PyModule, PyTuple};
+use objectprotocol::ObjectProtocol;
+use python::{Python, PythonObject, PyClone};
+
+fn ctypes(py: Python) -> PyResult<PyModule> {
+    py.import("ctypes")
+}
+
+fn address_from_int(py: Python, value: PyObject) -> PyResult<*mut c_void> {
+    if value == py.None() {
+        Ok(ptr::null_mut())
+    } else {
+        Ok(value.extract::<usize>(py)? as *mut c_void)
+    }
+}
+
+/// Gets the address referred to by a ctypes object.
+///
+/// Accepts all the objects that can be converted to a `c_void_p` using `ctypes.cast()`:
+/// `c_void_p` and other pointer instances, `byref()` results (including their offset),
+/// arrays, `c_char_p` instances, `CFUNCTYPE` callbacks, integers and `None`
+/// (which is converted to a null pointer).
+///
+/// Raises `ctypes.ArgumentError` for other objects.
+pub fn as_ptr(py: Python, obj: &PyObject) -> PyResult<*mut c_void> {
+    let ctypes = ctypes(py)?;
+    let void_p = ctypes.get(py, "c_void_p")?;
+    let value = ctypes.call(py, "cast", (obj, void_p), None)?.getattr(py, "value")?;
+    address_from_int(py, value)
+}
+
+/// Gets the address and the size (in bytes) of the memory block of a ctypes instance,
+/// e.g. an array or a structure.
+///
+/// This is equivalent to `(ctypes.addressof(obj), ctypes.sizeof(obj))`.
+pub fn buffer(py: Python, obj: &PyObject) -> PyResult<(*mut c_void, usize)> {
+    let ctypes = ctypes(py)?;
+    let address = address_from_int(py, ctypes.call(py, "addressof", (obj,), None)?)?;
+    let size = ctypes.call(py, "sizeof", (obj,), None)?.extract::<usize>(py)?;
+    Ok((address, size))
+}
+
+/// Creates a `ctypes.c_void_p` instance holding `ptr`.
+pub fn void_p(py: Python, ptr: *mut c_void) -> PyResult<PyObject> {
+    ctypes(py)?.call(py, "c_void_p", (ptr as usize,), None)
+}
+
+/// Creates an instance of the ctypes type `ty` that uses the memory at `ptr`,
+/// by calling `ty.from_address(ptr)`.
+///
+/// # Safety
+/// `ptr` must point to a memory block that is valid for `ty`, and must stay valid as
+/// long as the returned object is used.
+pub unsafe fn from_address(py: Python, ty: &PyObject, ptr: *mut c_void) -> PyResult<PyObject> {
+    ty.call_method(py, "from_address", (ptr as usize,), None)
+}
+
+/// Wraps a C function pointer as a ctypes function object, which can be called from Python.
+///
+/// `restype` and `argtypes` are the ctypes types of the result and of the arguments,
+/// as they would be passed to `ctypes.CFUNCTYPE()`.
+///
+/// # Safety
+/// `ptr` must point to a function whose signature matches `restype` and `argtypes`,
+/// using the C calling convention.
+pub unsafe fn function(py: Python, ptr: *const c_void, restype: &PyObject, argtypes: &[PyObject])
+    -> PyResult<PyObject>
+{
+    let ctypes = ctypes(py)?;
+    let mut args = Vec::with_capacity(argtypes.len() + 1);
+    args.push(restype.clone_ref(py));
+    args.extend(argtypes.iter().map(|t| t.clone_ref(py)));
+    let prototype = ctypes.get(py, "CFUNCTYPE")?.call(py, PyTuple::new(py, &args), None)?;
+    prototype.call(py, (ptr as usize,), None)
+}
+
+#[cfg(test)]
+mod test {
+    use std::ptr;
+    use libc::c_void;
+    use python::{Python, PyClone};
+    use objects::PyDict;
+    use objectprotocol::ObjectProtocol;
+    use super::*;
+
+    extern "C" fn add(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    #[test]
+    fn test_as_ptr() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let d = PyDict::new(py);
+        py.run("import ctypes\narr = (ctypes.c_int32 * 4)(1, 2, 3, 4)", None, Some(&d)).unwrap();
+        let arr = d.get_item(py, "arr").unwrap();
+        let (ptr, len) = buffer(py, &arr).unwrap();
+        assert_eq!(len, 16);
+        assert_eq!(as_ptr(py, &arr).unwrap(), ptr);
+        let byref = py.eval("ctypes.byref(arr, 4)", None, Some(&d)).unwrap();
+        assert_eq!(as_ptr(py, &byref).unwrap() as usize, ptr as usize + 4);
+        assert_eq!(as_ptr(py, &py.None()).unwrap(), ptr::null_mut());
+        assert!(as_ptr(py, &py.eval("ctypes.c_int(5)", None, Some(&d)).unwrap()).is_err());
+
+        let p = void_p(py, ptr).unwrap();
+        assert_eq!(as_ptr(py, &p).unwrap(), ptr);
+        let ty = py.eval("ctypes.c_int32 * 2", None, Some(&d)).unwrap();
+        let view = unsafe { from_address(py, &ty, ptr) }.unwrap();
+        assert_eq!(view.get_item(py, 1).unwrap().extract::<i32>(py).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_function() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let c_int32 = py.eval("__import__('ctypes').c_int32", None, None).unwrap();
+        let f = unsafe {
+            function(py, add as *const c_void, &c_int32, &[c_int32.clone_ref(py), c_int32.clone_ref(py)])
+        }.unwrap();
+        assert_eq!(f.call(py, (40, 2), None).unwrap().extract::<i32>(py).unwrap(), 42);
+        assert_eq!(as_ptr(py, &f).unwrap() as *const c_void, add as *const c_void);
+    }
+}
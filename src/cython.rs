@@ -0,0 +1,174 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Access to the C functions and variables exported by Cython modules.
+//!
+//! Cython modules export their `cdef api` and `cpdef` functions (and variables declared
+//! `cdef public api`) in the `__pyx_capi__` dict of the module. Each entry is a capsule
+//! named after the C signature of the function (or the C type of the variable),
+//! e.g. `"double (double, int __pyx_skip_dispatch)"`.
+//!
+//! Like the code generated by Cython for `cimport`, the functions of this module check
+//! that the signature matches the expected one before giving access to the pointer.
+//! The expected signature can be found in the C file generated by Cython for the module,
+//! or by inspecting the capsule in Python.
+//!
+//! ```ignore
+//! use std::mem;
+//! use cpython::{Python, cython};
+//!
+//! // Declared as `cpdef double clip(double x, double lo, double hi)` in `mylib/fastmath.pyx`
+//! type Clip = unsafe extern "C" fn(f64, f64, f64, i32) -> f64;
+//!
+//! let gil = Python::acquire_gil();
+//! let py = gil.python();
+//! let ptr = cython::import_function(py, "mylib.fastmath", "clip",
+//!                                   "double (double, double, double, int __pyx_skip_dispatch)").unwrap();
+//! let clip: Clip = unsafe { mem::transmute(ptr) };
+//! assert_eq!(unsafe { clip(1.5, 0.0, 1.0, 0) }, 1.0);
+//! ```
+
+use std::ffi::CStr;
+use libc::c_void;
+use ffi;
+use err::{PyErr, PyResult};
+use exc;
+use objects::{PyObject, PyCapsule};
+use objectprotocol::ObjectProtocol;
+use python::{Python, PythonObject};
+
+/// Gets the name of a capsule, which is the signature for Cython exports.
+fn signature(capsule: &PyCapsule) -> Option<&CStr> {
+    let name = unsafe { ffi::PyCapsule_GetName(capsule.as_object().as_ptr()) };
+    if name.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(name) })
+    }
+}
+
+fn import(py: Python, module: &str, name: &str, kind: &str, expected: &str) -> PyResult<*mut c_void> {
+    let capi = py.import(module)?.get(py, "__pyx_capi__")?;
+    let capsule = match capi.get_item(py, name) {
+        Ok(capsule) => capsule,
+        Err(ref e) if e.matches(py, py.get_type::<exc::KeyError>()) => {
+            return Err(PyErr::new::<exc::ImportError, _>(py,
+                format!("{} does not export expected C {} {}", module, kind, name)));
+        }
+        Err(e) => return Err(e),
+    };
+    let capsule = capsule.cast_into::<PyCapsule>(py)?;
+    let actual = match signature(&capsule) {
+        Some(actual) if actual.to_bytes() == expected.as_bytes() => actual,
+        actual => {
+            return Err(PyErr::new::<exc::TypeError, _>(py,
+                format!("C {} {}.{} has wrong signature (expected {}, got {})", kind, module, name,
+                    expected, actual.map(|s| s.to_string_lossy()).unwrap_or_default())));
+        }
+    };
+    let ptr = unsafe { ffi::PyCapsule_GetPointer(capsule.as_object().as_ptr(), actual.as_ptr()) };
+    if ptr.is_null() {
+        return Err(PyErr::fetch(py));
+    }
+    Ok(ptr)
+}
+
+/// Gets the pointer to the C function `name` exported by the Cython module `module`.
+///
+/// `signature` is the signature of the function, as written by Cython:
+/// the return type followed by the argument types (and names,
+/// for the `__pyx_skip_dispatch` argument of `cpdef` functions), e.g. `"int (int, int)"`.
+///
+/// The pointer can be converted to a function pointer with `mem::transmute`, for
+/// architectures where data and function pointers have the same size.
+///
+/// Raises `ImportError` if the module doesn't export the function and `TypeError`
+/// if its signature doesn't match `signature`.
+pub fn import_function(py: Python, module: &str, name: &str, signature: &str) -> PyResult<*mut c_void> {
+    import(py, module, name, "function", signature)
+}
+
+/// Gets the pointer to the C variable `name` exported by the Cython module `module`.
+///
+/// `type_name` is the C type of the variable, as written by Cython, e.g. `"int"`.
+///
+/// Raises `ImportError` if the module doesn't export the variable and `TypeError`
+/// if its type doesn't match `type_name`.
+pub fn import_variable(py: Python, module: &str, name: &str, type_name: &str) -> PyResult<*mut c_void> {
+    import(py, module, name, "variable", type_name)
+}
+
+/// Gets the signatures of the functions and variables exported by the Cython module `module`,
+/// as `(name, signature)` pairs.
+pub fn exports(py: Python, module: &str) -> PyResult<Vec<(String, String)>> {
+    let capi = py.import(module)?.get(py, "__pyx_capi__")?;
+    let mut exports = Vec::new();
+    for item in capi.call_method(py, "items", ::NoArgs, None)?.iter(py)? {
+        let (name, capsule): (String, PyObject) = item?.extract(py)?;
+        let capsule = capsule.cast_into::<PyCapsule>(py)?;
+        let signature = signature(&capsule).map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        exports.push((name, signature));
+    }
+    Ok(exports)
+}
+
+#[cfg(test)]
+mod test {
+    use std::mem;
+    use libc::{c_int, c_void};
+    use python::Python;
+    use objects::{PyCapsule, PyDict, PyModule};
+    use objectprotocol::ObjectProtocol;
+    use super::*;
+
+    extern "C" fn add(a: c_int, b: c_int) -> c_int {
+        a + b
+    }
+
+    static mut COUNTER: c_int = 42;
+
+    #[test]
+    fn test_import() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let capi = PyDict::new(py);
+        capi.set_item(py, "add", PyCapsule::new(py, add as *mut c_void, "int (int, int)").unwrap()).unwrap();
+        let counter = unsafe { &mut COUNTER };
+        capi.set_item(py, "counter", PyCapsule::new_data(py, counter, "int").unwrap()).unwrap();
+        let m = PyModule::new(py, "fake_cython_module").unwrap();
+        m.add(py, "__pyx_capi__", capi).unwrap();
+        py.import("sys").unwrap().get(py, "modules").unwrap()
+            .set_item(py, "fake_cython_module", m).unwrap();
+
+        let f = import_function(py, "fake_cython_module", "add", "int (int, int)").unwrap();
+        let f: extern "C" fn(c_int, c_int) -> c_int = unsafe { mem::transmute(f) };
+        assert_eq!(f(40, 2), 42);
+        let v = import_variable(py, "fake_cython_module", "counter", "int").unwrap();
+        assert_eq!(unsafe { *(v as *const c_int) }, 42);
+
+        let e = import_function(py, "fake_cython_module", "add", "long (long, long)").unwrap_err();
+        assert!(e.matches(py, py.get_type::<exc::TypeError>()));
+        let e = import_function(py, "fake_cython_module", "sub", "int (int, int)").unwrap_err();
+        assert!(e.matches(py, py.get_type::<exc::ImportError>()));
+
+        let mut exports = exports(py, "fake_cython_module").unwrap();
+        exports.sort();
+        assert_eq!(exports, vec![("add".to_owned(), "int (int, int)".to_owned()),
+                                 ("counter".to_owned(), "int".to_owned())]);
+    }
+}
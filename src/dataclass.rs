@@ -0,0 +1,224 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Python dataclasses mirroring Rust structs.
+//!
+//! `py_dataclass!` defines a Rust struct together with a Python
+//! [dataclass](https://docs.python.org/3/library/dataclasses.html) having the same fields.
+//! The dataclass type is created with `dataclasses.make_dataclass()` the first time it is used,
+//! and values are converted in both directions with `ToPyObject` and `FromPyObject`.
+//!
+//! Unlike a `py_class!`, the Python objects don't reference the Rust value: converting copies
+//! each field. Dataclasses require Python 3.7 or newer; on older versions, creating the
+//! dataclass type raises `ImportError`.
+//!
+//! ```
+//! #[macro_use] extern crate cpython;
+//! use cpython::{Python, PyDict, ToPyObject};
+//! use cpython::dataclass::Dataclass;
+//!
+//! py_dataclass!(pub struct Point {
+//!     x: i32,
+//!     y: i32,
+//! });
+//!
+//! fn main() {
+//!     let gil = Python::acquire_gil();
+//!     let py = gil.python();
+//! #   if py.version_info().unwrap() < (3, 7) { return; }
+//!     let locals = PyDict::new(py);
+//!     locals.set_item(py, "Point", Point::dataclass_type(py).unwrap()).unwrap();
+//!     locals.set_item(py, "p", Point { x: 1, y: 2 }.to_py_object(py)).unwrap();
+//!     py.run("assert p == Point(x=1, y=2)", None, Some(&locals)).unwrap();
+//!
+//!     let p: Point = py.eval("Point(3, 4)", None, Some(&locals)).unwrap().extract(py).unwrap();
+//!     assert_eq!((p.x, p.y), (3, 4));
+//! }
+//! ```
+
+use std::cell::UnsafeCell;
+use err::{PyErr, PyResult};
+use exc;
+use objects::{PyObject, PyList, PyType};
+use python::{Python, PyClone, PythonObject};
+use conversion::ToPyObject;
+
+/// A Rust type mirrored by a Python dataclass; implemented by `py_dataclass!`.
+pub trait Dataclass: Sized {
+    /// Gets the Python dataclass type, creating it on first use.
+    fn dataclass_type(py: Python) -> PyResult<PyType>;
+
+    /// Creates an instance of the dataclass holding a copy of the fields of `self`.
+    fn to_dataclass(&self, py: Python) -> PyResult<PyObject>;
+
+    /// Copies the fields of an instance of the dataclass.
+    ///
+    /// Raises `TypeError` if `obj` is not an instance of the dataclass type.
+    fn from_dataclass(py: Python, obj: &PyObject) -> PyResult<Self>;
+}
+
+/// Storage for the type created by `py_dataclass!`. Only accessed with the GIL held.
+#[doc(hidden)]
+pub struct TypeCache(pub UnsafeCell<Option<PyType>>);
+
+unsafe impl Sync for TypeCache { }
+
+impl TypeCache {
+    pub fn get_or_create(&self, py: Python, name: &str, fields: &[&str]) -> PyResult<PyType> {
+        if let Some(ty) = unsafe { (*self.0.get()).as_ref() } {
+            return Ok(ty.clone_ref(py));
+        }
+        // The cache isn't borrowed while creating the type: the import can release the GIL,
+        // letting another thread create the type too.
+        let fields: Vec<PyObject> = fields.iter().map(|f| f.to_py_object(py).into_object()).collect();
+        let ty = py.import("dataclasses")?
+            .call(py, "make_dataclass", (name, PyList::new(py, &fields)), None)?
+            .cast_into::<PyType>(py)?;
+        // Keep the type stored first, so that all the instances have the same type.
+        let cached = unsafe { &mut *self.0.get() };
+        if let Some(ty) = cached.as_ref() {
+            return Ok(ty.clone_ref(py));
+        }
+        *cached = Some(ty.clone_ref(py));
+        Ok(ty)
+    }
+}
+
+#[doc(hidden)]
+pub fn check_instance(py: Python, ty: &PyType, obj: &PyObject) -> PyResult<()> {
+    if ty.is_instance(py, obj) {
+        Ok(())
+    } else {
+        Err(PyErr::new::<exc::TypeError, _>(py, format!("expected an instance of {}", ty.name(py))))
+    }
+}
+
+/// Defines a struct mirrored by a Python dataclass.
+///
+/// Syntax: `py_dataclass!(struct Name { field: Type, ... })`, optionally with `pub`
+/// before `struct`, which also makes the fields public.
+/// Attributes (including doc comments) are allowed on the struct and on the fields.
+/// All field types must implement `ToPyObject` and `FromPyObject`.
+///
+/// The macro implements the `Dataclass` trait for the struct, as well as `ToPyObject`
+/// and `FromPyObject` using the dataclass.
+/// `ToPyObject` panics if the dataclass type cannot be created; use
+/// `Dataclass::to_dataclass()` to handle that error.
+///
+/// See the [`dataclass` module](dataclass/index.html) for an example.
+#[macro_export]
+macro_rules! py_dataclass {
+    ($(#[$attr:meta])* struct $name:ident {
+        $( $(#[$fattr:meta])* $field:ident : $ty:ty ),* $(,)*
+    }) => {
+        $(#[$attr])*
+        struct $name {
+            $( $(#[$fattr])* $field: $ty ),*
+        }
+        py_dataclass_impl! { $name { $( $field : $ty ),* } }
+    };
+    ($(#[$attr:meta])* pub struct $name:ident {
+        $( $(#[$fattr:meta])* $field:ident : $ty:ty ),* $(,)*
+    }) => {
+        $(#[$attr])*
+        pub struct $name {
+            $( $(#[$fattr])* pub $field: $ty ),*
+        }
+        py_dataclass_impl! { $name { $( $field : $ty ),* } }
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! py_dataclass_impl {
+    ($name:ident { $( $field:ident : $ty:ty ),* }) => {
+        impl $crate::dataclass::Dataclass for $name {
+            fn dataclass_type(py: $crate::Python) -> $crate::PyResult<$crate::PyType> {
+                static TYPE: $crate::dataclass::TypeCache =
+                    $crate::dataclass::TypeCache(::std::cell::UnsafeCell::new(None));
+                TYPE.get_or_create(py, stringify!($name), &[ $( stringify!($field) ),* ])
+            }
+
+            fn to_dataclass(&self, py: $crate::Python) -> $crate::PyResult<$crate::PyObject> {
+                let kwargs = $crate::PyDict::new(py);
+                $( kwargs.set_item(py, stringify!($field), &self.$field)?; )*
+                <$name as $crate::dataclass::Dataclass>::dataclass_type(py)?
+                    .call(py, $crate::NoArgs, Some(&kwargs))
+            }
+
+            fn from_dataclass(py: $crate::Python, obj: &$crate::PyObject) -> $crate::PyResult<$name> {
+                let ty = <$name as $crate::dataclass::Dataclass>::dataclass_type(py)?;
+                $crate::dataclass::check_instance(py, &ty, obj)?;
+                Ok($name {
+                    $( $field: $crate::ObjectProtocol::getattr(obj, py, stringify!($field))?
+                        .extract::<$ty>(py)? ),*
+                })
+            }
+        }
+
+        impl $crate::ToPyObject for $name {
+            type ObjectType = $crate::PyObject;
+
+            fn to_py_object(&self, py: $crate::Python) -> $crate::PyObject {
+                $crate::dataclass::Dataclass::to_dataclass(self, py).unwrap()
+            }
+        }
+
+        impl <'s> $crate::FromPyObject<'s> for $name {
+            fn extract(py: $crate::Python, obj: &'s $crate::PyObject) -> $crate::PyResult<$name> {
+                $crate::dataclass::Dataclass::from_dataclass(py, obj)
+            }
+        }
+    };
+}
+
+// The dataclasses module is new in Python 3.7.
+#[cfg(all(test, Py_3_7))]
+mod test {
+    use python::Python;
+    use conversion::ToPyObject;
+    use objects::PyDict;
+    use super::Dataclass;
+
+    py_dataclass!(struct Item {
+        /// the name
+        name: String,
+        tags: Vec<String>,
+        price: Option<f64>,
+    });
+
+    #[test]
+    fn test_roundtrip() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let item = Item { name: "pen".to_owned(), tags: vec!["office".to_owned()], price: None };
+        let d = PyDict::new(py);
+        d.set_item(py, "Item", Item::dataclass_type(py).unwrap()).unwrap();
+        d.set_item(py, "item", item.to_py_object(py)).unwrap();
+        py.run("import dataclasses\n\
+                assert dataclasses.is_dataclass(Item) and Item.__name__ == 'Item'\n\
+                assert item == Item('pen', ['office'], None)\n\
+                assert [f.name for f in dataclasses.fields(Item)] == ['name', 'tags', 'price']",
+            None, Some(&d)).unwrap();
+
+        let item: Item = py.eval("Item('ink', [], 2.5)", None, Some(&d)).unwrap().extract(py).unwrap();
+        assert_eq!((&item.name[..], item.tags.len(), item.price), ("ink", 0, Some(2.5)));
+        assert!(py.eval("Item", None, Some(&d)).unwrap().extract::<Item>(py).is_err());
+        assert!(py.eval("Item(1, [], None)", None, Some(&d)).unwrap().extract::<Item>(py).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Support for the IPython/Jupyter [rich display protocol](https://ipython.readthedocs.io/en/stable/config/integrating.html).
+//!
+//! IPython looks for methods such as `_repr_html_()`, `_repr_markdown_()`, `_repr_png_()`,
+//! `_repr_mimebundle_()` or `_ipython_display_()` on the objects it displays.
+//! These are regular methods, so they can be defined in a `py_class!` like any other method.
+//!
+//! `MimeBundle` helps to implement `_repr_mimebundle_()`, which returns the representations
+//! of an object for several MIME types at once:
+//!
+//! ```
+//! #[macro_use] extern crate cpython;
+//! use cpython::{PyObject, PyResult};
+//! use cpython::display::MimeBundle;
+//!
+//! py_class!(class Point |py| {
+//!     data x: i32;
+//!     data y: i32;
+//!
+//!     def _repr_mimebundle_(&self, include: Option<PyObject> = None, exclude: Option<PyObject> = None)
+//!         -> PyResult<MimeBundle>
+//!     {
+//!         let (x, y) = (*self.x(py), *self.y(py));
+//!         Ok(MimeBundle::new()
+//!             .text(py, &format!("Point({}, {})", x, y))
+//!             .html(py, &format!("<b>Point</b>({}, {})", x, y))
+//!             .json(py, vec![x, y]))
+//!     }
+//! });
+//! # fn main() {}
+//! ```
+
+use conversion::ToPyObject;
+use err::PyResult;
+use objects::{PyObject, PyDict, PyBytes};
+use python::{Python, PythonObject, PyClone};
+
+/// A set of representations of an object, keyed by MIME type, with optional metadata.
+///
+/// When converted to a Python object, a `MimeBundle` gives the value expected as
+/// return value of `_repr_mimebundle_()`: a `dict` mapping the MIME types to the data,
+/// or a `(data, metadata)` tuple if metadata was added.
+pub struct MimeBundle {
+    data: Vec<(String, PyObject)>,
+    metadata: Vec<(String, PyObject)>,
+}
+
+impl MimeBundle {
+    /// Creates an empty bundle.
+    pub fn new() -> MimeBundle {
+        MimeBundle { data: Vec::new(), metadata: Vec::new() }
+    }
+
+    /// Adds the representation for the MIME type `mime`.
+    ///
+    /// If the bundle already contains a representation for `mime`, it is replaced.
+    pub fn with<V: ToPyObject>(mut self, py: Python, mime: &str, value: V) -> MimeBundle {
+        let value = value.into_py_object(py).into_object();
+        self.data.retain(|entry| entry.0 != mime);
+        self.data.push((mime.to_owned(), value));
+        self
+    }
+
+    /// Adds metadata for the MIME type `mime`, e.g. `{"width": 100}` for an image.
+    pub fn with_metadata<V: ToPyObject>(mut self, py: Python, mime: &str, value: V) -> MimeBundle {
+        let value = value.into_py_object(py).into_object();
+        self.metadata.retain(|entry| entry.0 != mime);
+        self.metadata.push((mime.to_owned(), value));
+        self
+    }
+
+    /// Adds a `text/plain` representation.
+    pub fn text(self, py: Python, text: &str) -> MimeBundle {
+        self.with(py, "text/plain", text)
+    }
+
+    /// Adds a `text/html` representation.
+    pub fn html(self, py: Python, html: &str) -> MimeBundle {
+        self.with(py, "text/html", html)
+    }
+
+    /// Adds a `text/markdown` representation.
+    pub fn markdown(self, py: Python, markdown: &str) -> MimeBundle {
+        self.with(py, "text/markdown", markdown)
+    }
+
+    /// Adds a `text/latex` representation.
+    pub fn latex(self, py: Python, latex: &str) -> MimeBundle {
+        self.with(py, "text/latex", latex)
+    }
+
+    /// Adds an `image/svg+xml` representation.
+    pub fn svg(self, py: Python, svg: &str) -> MimeBundle {
+        self.with(py, "image/svg+xml", svg)
+    }
+
+    /// Adds an `image/png` representation, from the raw PNG data.
+    pub fn png(self, py: Python, png: &[u8]) -> MimeBundle {
+        self.with(py, "image/png", PyBytes::new(py, png))
+    }
+
+    /// Adds an `image/jpeg` representation, from the raw JPEG data.
+    pub fn jpeg(self, py: Python, jpeg: &[u8]) -> MimeBundle {
+        self.with(py, "image/jpeg", PyBytes::new(py, jpeg))
+    }
+
+    /// Adds an `application/json` representation; `value` must be JSON-serializable.
+    pub fn json<V: ToPyObject>(self, py: Python, value: V) -> MimeBundle {
+        self.with(py, "application/json", value)
+    }
+
+    /// Gets the representation for the MIME type `mime`.
+    pub fn get(&self, mime: &str) -> Option<&PyObject> {
+        self.data.iter().find(|entry| entry.0 == mime).map(|entry| &entry.1)
+    }
+
+    fn data_dict(&self, py: Python) -> PyResult<PyDict> {
+        let dict = PyDict::new(py);
+        for entry in &self.data {
+            dict.set_item(py, &entry.0, &entry.1)?;
+        }
+        Ok(dict)
+    }
+
+    fn metadata_dict(&self, py: Python) -> PyResult<PyDict> {
+        let dict = PyDict::new(py);
+        for entry in &self.metadata {
+            dict.set_item(py, &entry.0, &entry.1)?;
+        }
+        Ok(dict)
+    }
+
+    /// Displays the bundle using `IPython.display.display()`.
+    ///
+    /// This is intended for implementations of `_ipython_display_()`.
+    /// Raises `ImportError` if IPython is not available.
+    pub fn display(&self, py: Python) -> PyResult<()> {
+        let display = py.import("IPython.display")?;
+        let kwargs = PyDict::new(py);
+        kwargs.set_item(py, "raw", true)?;
+        kwargs.set_item(py, "metadata", self.metadata_dict(py)?)?;
+        display.call(py, "display", (self.data_dict(py)?,), Some(&kwargs))?;
+        Ok(())
+    }
+}
+
+impl Default for MimeBundle {
+    fn default() -> MimeBundle {
+        MimeBundle::new()
+    }
+}
+
+impl PyClone for MimeBundle {
+    fn clone_ref(&self, py: Python) -> MimeBundle {
+        let clone = |v: &Vec<(String, PyObject)>| {
+            v.iter().map(|entry| (entry.0.clone(), entry.1.clone_ref(py))).collect()
+        };
+        MimeBundle { data: clone(&self.data), metadata: clone(&self.metadata) }
+    }
+}
+
+impl ToPyObject for MimeBundle {
+    type ObjectType = PyObject;
+
+    fn to_py_object(&self, py: Python) -> PyObject {
+        // Inserting str keys into a fresh dict does not fail
+        let data = self.data_dict(py).unwrap();
+        if self.metadata.is_empty() {
+            data.into_object()
+        } else {
+            (data, self.metadata_dict(py).unwrap()).to_py_object(py).into_object()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use python::Python;
+    use conversion::ToPyObject;
+    use objects::PyDict;
+    use super::MimeBundle;
+
+    #[test]
+    fn test_bundle() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let bundle = MimeBundle::new()
+            .text(py, "plain")
+            .html(py, "<b>old</b>")
+            .html(py, "<b>html</b>")
+            .png(py, b"\x89PNG");
+        assert!(bundle.get("text/markdown").is_none());
+        let d = PyDict::new(py);
+        d.set_item(py, "b", bundle.to_py_object(py)).unwrap();
+        py.run("assert b == {'text/plain': 'plain', 'text/html': '<b>html</b>', 'image/png': b'\\x89PNG'}",
+            None, Some(&d)).unwrap();
+
+        let bundle = bundle.with_metadata(py, "image/png", vec![("width", 10)].into_iter()
+            .collect::<::std::collections::HashMap<_, _>>());
+        d.set_item(py, "b", bundle.to_py_object(py)).unwrap();
+        py.run("assert b[1] == {'image/png': {'width': 10}} and len(b[0]) == 3", None, Some(&d)).unwrap();
+    }
+}
@@ -0,0 +1,411 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Exchange of tensors with other libraries using the [DLPack](https://dmlc.github.io/dlpack/latest/)
+//! protocol.
+//!
+//! Python objects supporting DLPack (PyTorch tensors, JAX and CuPy arrays, ...) implement a
+//! `__dlpack__()` method that returns a capsule named `"dltensor"`, wrapping a `DLManagedTensor`.
+//! The consumer takes ownership of the tensor by renaming the capsule to `"used_dltensor"`;
+//! it is then responsible for calling the tensor's deleter.
+//!
+//! [`DLPackTensor`](struct.DLPackTensor.html) implements both sides of that exchange:
+//!
+//! ```
+//! use cpython::Python;
+//! use cpython::dlpack::DLPackTensor;
+//!
+//! let gil = Python::acquire_gil();
+//! let py = gil.python();
+//!
+//! // Producing: hand a Rust buffer over to Python without copying it.
+//! let tensor = DLPackTensor::from_vec(vec![1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0], vec![2, 3]);
+//! let capsule = tensor.into_capsule(py);
+//!
+//! // Consuming: take ownership of the tensor wrapped by a `"dltensor"` capsule.
+//! let tensor = DLPackTensor::from_capsule(py, &capsule).unwrap();
+//! assert_eq!(tensor.shape(), &[2, 3]);
+//! assert_eq!(tensor.as_slice::<f32>().unwrap()[4], 5.0);
+//! ```
+
+use std::{mem, ptr, slice};
+use std::ptr::NonNull;
+use libc::c_void;
+use ffi;
+use err::{self, PyErr, PyResult};
+use exc;
+use objects::{PyObject, PyCapsule};
+use objectprotocol::ObjectProtocol;
+use python::{Python, PythonObject};
+
+/// Type of the device on which the data of a tensor lives (`DLDeviceType` in `dlpack.h`).
+pub type DLDeviceType = i32;
+
+#[allow(non_upper_case_globals)] pub const kDLCPU: DLDeviceType = 1;
+#[allow(non_upper_case_globals)] pub const kDLCUDA: DLDeviceType = 2;
+#[allow(non_upper_case_globals)] pub const kDLCUDAHost: DLDeviceType = 3;
+#[allow(non_upper_case_globals)] pub const kDLOpenCL: DLDeviceType = 4;
+#[allow(non_upper_case_globals)] pub const kDLVulkan: DLDeviceType = 7;
+#[allow(non_upper_case_globals)] pub const kDLMetal: DLDeviceType = 8;
+#[allow(non_upper_case_globals)] pub const kDLVPI: DLDeviceType = 9;
+#[allow(non_upper_case_globals)] pub const kDLROCM: DLDeviceType = 10;
+#[allow(non_upper_case_globals)] pub const kDLROCMHost: DLDeviceType = 11;
+#[allow(non_upper_case_globals)] pub const kDLExtDev: DLDeviceType = 12;
+#[allow(non_upper_case_globals)] pub const kDLCUDAManaged: DLDeviceType = 13;
+#[allow(non_upper_case_globals)] pub const kDLOneAPI: DLDeviceType = 14;
+
+/// Type code of the tensor elements (`DLDataTypeCode` in `dlpack.h`).
+pub type DLDataTypeCode = u8;
+
+#[allow(non_upper_case_globals)] pub const kDLInt: DLDataTypeCode = 0;
+#[allow(non_upper_case_globals)] pub const kDLUInt: DLDataTypeCode = 1;
+#[allow(non_upper_case_globals)] pub const kDLFloat: DLDataTypeCode = 2;
+#[allow(non_upper_case_globals)] pub const kDLOpaqueHandle: DLDataTypeCode = 3;
+#[allow(non_upper_case_globals)] pub const kDLBfloat: DLDataTypeCode = 4;
+#[allow(non_upper_case_globals)] pub const kDLComplex: DLDataTypeCode = 5;
+#[allow(non_upper_case_globals)] pub const kDLBool: DLDataTypeCode = 6;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DLDevice {
+    pub device_type: DLDeviceType,
+    pub device_id: i32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DLDataType {
+    pub code: DLDataTypeCode,
+    pub bits: u8,
+    pub lanes: u16,
+}
+
+#[repr(C)]
+pub struct DLTensor {
+    pub data: *mut c_void,
+    pub device: DLDevice,
+    pub ndim: i32,
+    pub dtype: DLDataType,
+    pub shape: *mut i64,
+    /// May be null, which indicates a C-contiguous tensor.
+    pub strides: *mut i64,
+    pub byte_offset: u64,
+}
+
+#[repr(C)]
+pub struct DLManagedTensor {
+    pub dl_tensor: DLTensor,
+    pub manager_ctx: *mut c_void,
+    pub deleter: Option<unsafe extern "C" fn(*mut DLManagedTensor)>,
+}
+
+/// Trait implemented for the Rust types that have a DLPack data type.
+///
+/// # Safety
+/// `dtype()` must describe the memory layout of `Self` exactly.
+pub unsafe trait DLPackElement : Copy {
+    fn dtype() -> DLDataType;
+}
+
+macro_rules! impl_dlpack_element(
+    ($t:ty, $code:ident) => {
+        unsafe impl DLPackElement for $t {
+            #[inline]
+            fn dtype() -> DLDataType {
+                DLDataType { code: $code, bits: (mem::size_of::<$t>() * 8) as u8, lanes: 1 }
+            }
+        }
+    }
+);
+
+impl_dlpack_element!(u8, kDLUInt);
+impl_dlpack_element!(u16, kDLUInt);
+impl_dlpack_element!(u32, kDLUInt);
+impl_dlpack_element!(u64, kDLUInt);
+impl_dlpack_element!(i8, kDLInt);
+impl_dlpack_element!(i16, kDLInt);
+impl_dlpack_element!(i32, kDLInt);
+impl_dlpack_element!(i64, kDLInt);
+impl_dlpack_element!(f32, kDLFloat);
+impl_dlpack_element!(f64, kDLFloat);
+impl_dlpack_element!(bool, kDLBool);
+
+const DLTENSOR_NAME: &[u8] = b"dltensor\0";
+const USED_DLTENSOR_NAME: &[u8] = b"used_dltensor\0";
+
+/// An owned `DLManagedTensor`.
+///
+/// The tensor's deleter is called when the `DLPackTensor` is dropped,
+/// unless ownership was passed on using `into_capsule()` or `into_raw()`.
+pub struct DLPackTensor(NonNull<DLManagedTensor>);
+
+/// Keeps the memory of a tensor created by `DLPackTensor::from_vec()` alive.
+struct VecContext<T> {
+    managed: DLManagedTensor,
+    _data: Vec<T>,
+    shape: Vec<i64>,
+}
+
+unsafe extern "C" fn vec_deleter<T>(managed: *mut DLManagedTensor) {
+    // `managed` is the first field of the (heap-allocated) context
+    drop(Box::from_raw((*managed).manager_ctx as *mut VecContext<T>));
+}
+
+unsafe extern "C" fn capsule_destructor(capsule: *mut ffi::PyObject) {
+    // Only delete the tensor if no consumer took ownership of it.
+    let name = DLTENSOR_NAME.as_ptr() as *const _;
+    if ffi::PyCapsule_IsValid(capsule, name) != 0 {
+        let managed = ffi::PyCapsule_GetPointer(capsule, name) as *mut DLManagedTensor;
+        if let Some(deleter) = (*managed).deleter {
+            deleter(managed);
+        }
+    }
+}
+
+impl DLPackTensor {
+    /// Creates a C-contiguous tensor on the CPU that takes ownership of `data`.
+    ///
+    /// Panics if the number of elements described by `shape` is not `data.len()`.
+    pub fn from_vec<T: DLPackElement + 'static>(mut data: Vec<T>, shape: Vec<i64>) -> DLPackTensor {
+        assert_eq!(shape.iter().product::<i64>() as usize, data.len(),
+            "DLPackTensor::from_vec(): shape does not match the data length");
+        let mut ctx = Box::new(VecContext {
+            managed: DLManagedTensor {
+                dl_tensor: DLTensor {
+                    data: data.as_mut_ptr() as *mut c_void,
+                    device: DLDevice { device_type: kDLCPU, device_id: 0 },
+                    ndim: shape.len() as i32,
+                    dtype: T::dtype(),
+                    shape: ptr::null_mut(),
+                    strides: ptr::null_mut(),
+                    byte_offset: 0,
+                },
+                manager_ctx: ptr::null_mut(),
+                deleter: Some(vec_deleter::<T>),
+            },
+            _data: data,
+            shape,
+        });
+        ctx.managed.dl_tensor.shape = ctx.shape.as_mut_ptr();
+        let ctx = Box::into_raw(ctx);
+        unsafe {
+            (*ctx).managed.manager_ctx = ctx as *mut c_void;
+            DLPackTensor(NonNull::new_unchecked(&mut (*ctx).managed))
+        }
+    }
+
+    /// Takes ownership of a raw `DLManagedTensor`.
+    ///
+    /// # Safety
+    /// `managed` must point to a valid `DLManagedTensor` that is not owned by anybody else.
+    pub unsafe fn from_raw(managed: *mut DLManagedTensor) -> DLPackTensor {
+        DLPackTensor(NonNull::new(managed).expect("DLPackTensor::from_raw(): null pointer"))
+    }
+
+    /// Releases ownership of the tensor, returning the raw `DLManagedTensor`.
+    ///
+    /// The caller becomes responsible for calling its deleter.
+    pub fn into_raw(self) -> *mut DLManagedTensor {
+        let ptr = self.0.as_ptr();
+        mem::forget(self);
+        ptr
+    }
+
+    /// Takes ownership of the tensor wrapped by a `"dltensor"` capsule.
+    ///
+    /// The capsule is renamed to `"used_dltensor"`, which tells its producer that the
+    /// tensor was consumed.
+    /// Raises `ValueError` if the capsule is not a `"dltensor"` capsule, in particular if
+    /// it was already consumed.
+    pub fn from_capsule(py: Python, capsule: &PyCapsule) -> PyResult<DLPackTensor> {
+        unsafe {
+            let name = DLTENSOR_NAME.as_ptr() as *const _;
+            if ffi::PyCapsule_IsValid(capsule.as_object().as_ptr(), name) == 0 {
+                return Err(PyErr::new::<exc::ValueError, _>(py,
+                    "expected a 'dltensor' capsule; a DLPack capsule can only be consumed once"));
+            }
+            let managed = ffi::PyCapsule_GetPointer(capsule.as_object().as_ptr(), name);
+            if managed.is_null() {
+                return Err(PyErr::fetch(py));
+            }
+            err::error_on_minusone(py, ffi::PyCapsule_SetName(capsule.as_object().as_ptr(),
+                USED_DLTENSOR_NAME.as_ptr() as *const _))?;
+            Ok(DLPackTensor::from_raw(managed as *mut DLManagedTensor))
+        }
+    }
+
+    /// Takes ownership of the tensor exported by `obj.__dlpack__()`.
+    pub fn from_object(py: Python, obj: &PyObject) -> PyResult<DLPackTensor> {
+        let capsule = obj.call_method(py, "__dlpack__", ::NoArgs, None)?;
+        DLPackTensor::from_capsule(py, capsule.cast_as::<PyCapsule>(py)?)
+    }
+
+    /// Wraps the tensor into a `"dltensor"` capsule, suitable as return value of a
+    /// `__dlpack__()` method.
+    ///
+    /// If the capsule is never consumed, the tensor is deleted together with the capsule.
+    pub fn into_capsule(self, py: Python) -> PyCapsule {
+        unsafe {
+            let ptr = ffi::PyCapsule_New(self.into_raw() as *mut c_void,
+                DLTENSOR_NAME.as_ptr() as *const _, Some(capsule_destructor));
+            err::cast_from_owned_ptr_or_panic(py, ptr)
+        }
+    }
+
+    /// Gets the underlying `DLTensor`.
+    #[inline]
+    pub fn tensor(&self) -> &DLTensor {
+        unsafe { &(*self.0.as_ptr()).dl_tensor }
+    }
+
+    #[inline]
+    pub fn device(&self) -> DLDevice {
+        self.tensor().device
+    }
+
+    #[inline]
+    pub fn dtype(&self) -> DLDataType {
+        self.tensor().dtype
+    }
+
+    #[inline]
+    pub fn ndim(&self) -> usize {
+        self.tensor().ndim as usize
+    }
+
+    /// Returns the shape of the tensor, one entry per dimension.
+    pub fn shape(&self) -> &[i64] {
+        let t = self.tensor();
+        if t.ndim == 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(t.shape, t.ndim as usize) }
+    }
+
+    /// Returns the strides of the tensor, in number of elements.
+    ///
+    /// Returns `None` if the tensor is C-contiguous and doesn't specify strides.
+    pub fn strides(&self) -> Option<&[i64]> {
+        let t = self.tensor();
+        if t.strides.is_null() {
+            None
+        } else if t.ndim == 0 {
+            Some(&[])
+        } else {
+            unsafe { Some(slice::from_raw_parts(t.strides, t.ndim as usize)) }
+        }
+    }
+
+    /// Gets the total number of elements in the tensor.
+    pub fn item_count(&self) -> usize {
+        self.shape().iter().product::<i64>() as usize
+    }
+
+    /// Gets whether the tensor is C-contiguous.
+    pub fn is_c_contiguous(&self) -> bool {
+        let strides = match self.strides() {
+            None => return true,
+            Some(strides) => strides,
+        };
+        let mut expected = 1;
+        for (&dim, &stride) in self.shape().iter().zip(strides).rev() {
+            if dim != 1 && stride != expected {
+                return false;
+            }
+            expected *= dim;
+        }
+        true
+    }
+
+    /// Gets the tensor data as a slice.
+    ///
+    /// Returns `None` if the data doesn't live in CPU memory, if the tensor is not
+    /// C-contiguous, or if `T` does not match the tensor's data type.
+    pub fn as_slice<T: DLPackElement>(&self) -> Option<&[T]> {
+        let t = self.tensor();
+        if t.device.device_type != kDLCPU || t.dtype != T::dtype() || !self.is_c_contiguous() {
+            return None;
+        }
+        let len = self.item_count();
+        if len == 0 {
+            return Some(&[]);
+        }
+        unsafe {
+            let data = (t.data as *const u8).offset(t.byte_offset as isize) as *const T;
+            Some(slice::from_raw_parts(data, len))
+        }
+    }
+}
+
+impl Drop for DLPackTensor {
+    fn drop(&mut self) {
+        unsafe {
+            let managed = self.0.as_ptr();
+            if let Some(deleter) = (*managed).deleter {
+                deleter(managed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use python::{Python, PythonObject};
+    use objects::PyDict;
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let capsule = DLPackTensor::from_vec(vec![1i32, 2, 3, 4, 5, 6], vec![3, 2]).into_capsule(py);
+        let tensor = DLPackTensor::from_capsule(py, &capsule).unwrap();
+        assert_eq!(tensor.device(), DLDevice { device_type: kDLCPU, device_id: 0 });
+        assert_eq!(tensor.dtype(), DLDataType { code: kDLInt, bits: 32, lanes: 1 });
+        assert_eq!(tensor.shape(), &[3, 2]);
+        assert_eq!(tensor.strides(), None);
+        assert_eq!(tensor.as_slice::<i32>(), Some(&[1, 2, 3, 4, 5, 6][..]));
+        assert!(tensor.as_slice::<u32>().is_none());
+
+        // a capsule can only be consumed once
+        assert!(DLPackTensor::from_capsule(py, &capsule).is_err());
+    }
+
+    #[test]
+    fn test_unconsumed_capsule() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        // dropping the capsule calls the deleter
+        drop(DLPackTensor::from_vec(vec![0u8; 16], vec![4, 4]).into_capsule(py));
+    }
+
+    #[test]
+    fn test_from_object() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let d = PyDict::new(py);
+        d.set_item(py, "__builtins__", py.builtins().unwrap()).unwrap();
+        let capsule = DLPackTensor::from_vec(vec![0.5f64, 1.5], vec![2]).into_capsule(py);
+        d.set_item(py, "capsule", capsule).unwrap();
+        py.run("class T:\n    def __dlpack__(self, stream=None):\n        return capsule\nt = T()",
+            Some(&d), None).unwrap();
+        let obj = d.get_item(py, "t").unwrap();
+        let tensor = DLPackTensor::from_object(py, obj.as_object()).unwrap();
+        assert_eq!(tensor.as_slice::<f64>(), Some(&[0.5, 1.5][..]));
+    }
+}
@@ -0,0 +1,364 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Support for processes that `fork()`, e.g. using the `fork` start method of `multiprocessing`.
+//!
+//! A forked child process only contains the thread that called `fork()`.
+//! Threads of the parent (e.g. the workers of a thread pool) don't exist in the child,
+//! and locks held by those threads are never released, so any Rust state relying on them
+//! must be re-created in the child.
+//!
+//! There are two tools for this:
+//!
+//!  * `register_at_fork()` registers Rust functions to be called around `fork()`,
+//!    using Python's `os.register_at_fork()` (Python 3.7 and newer).
+//!  * `ForkSafe<T>` holds a value that is lazily re-created in each process.
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use cpython::Python;
+//! use cpython::fork::ForkSafe;
+//!
+//! // e.g. file handles or a thread pool; usually stored in a `static`
+//! let state: ForkSafe<HashMap<String, u32>> = ForkSafe::new();
+//!
+//! let gil = Python::acquire_gil();
+//! let py = gil.python();
+//! assert!(state.get_or_init(py, |_| HashMap::new()).is_empty());
+//! ```
+
+use std::cell::{Ref, RefCell, UnsafeCell};
+use std::{mem, ptr, thread};
+use std::thread::ThreadId;
+use libc::{self, c_char};
+use ffi;
+use err::PyResult;
+use function;
+use objects::PyDict;
+use python::Python;
+
+/// A value that is created at most once per process.
+///
+/// The value is created on first use by `get_or_init()`; when used in a process forked
+/// after the value was created, a new value is created.
+/// The value inherited from the parent process is leaked, not dropped: dropping state
+/// such as a thread pool in the child would wait for threads that don't exist there.
+///
+/// Like `GILProtected`, `ForkSafe` uses the GIL to synchronize accesses to the value.
+/// Unlike a `Mutex`, the GIL is always in a consistent state in the forked child.
+///
+/// To use a `ForkSafe` in a `static`, enable the `nightly` feature, which makes `new()`
+/// a `const fn`, or use a crate like `lazy_static`.
+pub struct ForkSafe<T> {
+    cell: RefCell<Option<(u32, T)>>,
+    /// The threads running `init`.
+    initializing: RefCell<Vec<ThreadId>>,
+}
+
+unsafe impl<T: Send> Send for ForkSafe<T> { }
+unsafe impl<T: Send> Sync for ForkSafe<T> { }
+
+impl<T> ForkSafe<T> {
+    /// Creates an empty `ForkSafe`.
+    #[cfg(feature="nightly")]
+    pub const fn new() -> ForkSafe<T> {
+        ForkSafe { cell: RefCell::new(None), initializing: RefCell::new(Vec::new()) }
+    }
+
+    /// Creates an empty `ForkSafe`.
+    #[cfg(not(feature="nightly"))]
+    pub fn new() -> ForkSafe<T> {
+        ForkSafe { cell: RefCell::new(None), initializing: RefCell::new(Vec::new()) }
+    }
+
+    /// Gets the value for the current process, calling `init` to create it if needed.
+    ///
+    /// Panics if called recursively from `init`.
+    /// If `init` releases the GIL, another thread may call `init` too; the value
+    /// stored first is kept, and the other one is dropped.
+    pub fn get_or_init<'a, F>(&'a self, py: Python<'a>, init: F) -> Ref<'a, T>
+        where F: FnOnce(Python) -> T
+    {
+        let pid = current_pid();
+        if !self.is_current(pid) {
+            let value = {
+                let _initializing = Initializing::start(&self.initializing);
+                init(py)
+            };
+            if !self.is_current(pid) {
+                let mut cell = self.cell.borrow_mut();
+                // Either empty, or inherited from the parent process.
+                mem::forget(cell.take());
+                *cell = Some((pid, value));
+            }
+        }
+        Ref::map(self.cell.borrow(), |v| &v.as_ref().unwrap().1)
+    }
+
+    fn is_current(&self, pid: u32) -> bool {
+        match *self.cell.borrow() {
+            Some((owner, _)) => owner == pid,
+            None => false,
+        }
+    }
+
+    /// Drops the value, if it was created in the current process.
+    ///
+    /// The next call to `get_or_init()` creates a new value.
+    pub fn reset(&self, _py: Python) {
+        if let Some(value) = self.cell.borrow_mut().take() {
+            if value.0 != current_pid() {
+                mem::forget(value);
+            }
+        }
+    }
+}
+
+impl<T> Default for ForkSafe<T> {
+    fn default() -> ForkSafe<T> {
+        ForkSafe::new()
+    }
+}
+
+fn current_pid() -> u32 {
+    unsafe { libc::getpid() as u32 }
+}
+
+/// Marks a `ForkSafe` as being initialized by the current thread, until dropped.
+struct Initializing<'a> {
+    threads: &'a RefCell<Vec<ThreadId>>,
+    id: ThreadId,
+}
+
+impl<'a> Initializing<'a> {
+    fn start(threads: &'a RefCell<Vec<ThreadId>>) -> Initializing<'a> {
+        let id = thread::current().id();
+        if threads.borrow().contains(&id) {
+            panic!("ForkSafe::get_or_init() called recursively from its init function");
+        }
+        threads.borrow_mut().push(id);
+        Initializing { threads, id }
+    }
+}
+
+impl<'a> Drop for Initializing<'a> {
+    fn drop(&mut self) {
+        let id = self.id;
+        self.threads.borrow_mut().retain(|&thread| thread != id);
+    }
+}
+
+/// The point of a `fork()` at which a handler registered with `register_at_fork()` is called.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ForkEvent {
+    /// In the parent process, before forking.
+    /// These handlers are called in reverse registration order.
+    Before,
+    /// In the parent process, after forking.
+    AfterInParent,
+    /// In the child process, after forking.
+    AfterInChild,
+}
+
+/// Handlers registered for one `ForkEvent`.
+struct Handlers {
+    /// Whether the Python callback running the handlers was registered.
+    registered: bool,
+    handlers: Vec<fn(Python)>,
+}
+
+/// The registered handlers, in the order of the `ForkEvent` variants; created on first use.
+/// Only accessed with the GIL held.
+struct Registry(UnsafeCell<Option<[Handlers; 3]>>);
+
+unsafe impl Sync for Registry { }
+
+static REGISTRY: Registry = Registry(UnsafeCell::new(None));
+
+/// Gets the handlers registered for `event`.
+/// The reference must not be kept while running Python code.
+unsafe fn handlers(event: ForkEvent) -> &'static mut Handlers {
+    let new = || Handlers { registered: false, handlers: Vec::new() };
+    &mut (*REGISTRY.0.get()).get_or_insert_with(|| [new(), new(), new()])[index(event)]
+}
+
+fn index(event: ForkEvent) -> usize {
+    match event {
+        ForkEvent::Before => 0,
+        ForkEvent::AfterInParent => 1,
+        ForkEvent::AfterInChild => 2,
+    }
+}
+
+fn run_handlers(py: Python, event: ForkEvent) {
+    // Copy the list, so that handlers can register other handlers.
+    let mut handlers = unsafe { handlers(event).handlers.clone() };
+    if event == ForkEvent::Before {
+        handlers.reverse();
+    }
+    for handler in handlers {
+        handler(py);
+    }
+}
+
+macro_rules! fork_callback {
+    ($name:ident, $event:expr) => {
+        unsafe extern "C" fn $name(_slf: *mut ffi::PyObject, _args: *mut ffi::PyObject) -> *mut ffi::PyObject {
+            function::handle_callback(stringify!($name), function::PyObjectCallbackConverter, |py| {
+                run_handlers(py, $event);
+                Ok(py.None())
+            })
+        }
+    }
+}
+
+fork_callback!(run_before_fork, ForkEvent::Before);
+fork_callback!(run_after_fork_in_parent, ForkEvent::AfterInParent);
+fork_callback!(run_after_fork_in_child, ForkEvent::AfterInChild);
+
+static mut METHOD_DEFS: [ffi::PyMethodDef; 3] = [
+    ffi::PyMethodDef {
+        ml_name: b"run_before_fork\0" as *const u8 as *const c_char,
+        ml_meth: Some(run_before_fork),
+        ml_flags: ffi::METH_NOARGS,
+        ml_doc: ptr::null(),
+    },
+    ffi::PyMethodDef {
+        ml_name: b"run_after_fork_in_parent\0" as *const u8 as *const c_char,
+        ml_meth: Some(run_after_fork_in_parent),
+        ml_flags: ffi::METH_NOARGS,
+        ml_doc: ptr::null(),
+    },
+    ffi::PyMethodDef {
+        ml_name: b"run_after_fork_in_child\0" as *const u8 as *const c_char,
+        ml_meth: Some(run_after_fork_in_child),
+        ml_flags: ffi::METH_NOARGS,
+        ml_doc: ptr::null(),
+    },
+];
+
+fn keyword(event: ForkEvent) -> &'static str {
+    match event {
+        ForkEvent::Before => "before",
+        ForkEvent::AfterInParent => "after_in_parent",
+        ForkEvent::AfterInChild => "after_in_child",
+    }
+}
+
+/// Registers `handler` to be called when the process forks,
+/// at the point of the fork designated by `event`.
+///
+/// The handlers are called with the GIL held, when the process is forked by Python
+/// (`os.fork()`, `multiprocessing`, ...). Like with `os.register_at_fork()`, they are not
+/// called when the process is forked by C code that doesn't call `PyOS_BeforeFork()` and
+/// friends.
+///
+/// Raises `AttributeError` before Python 3.7, which lacks `os.register_at_fork()`.
+pub fn register_at_fork(py: Python, event: ForkEvent, handler: fn(Python)) -> PyResult<()> {
+    // The registry isn't borrowed while calling os.register_at_fork(), which can
+    // release the GIL; marking the event first prevents registering the callback twice.
+    if !unsafe { handlers(event) }.registered {
+        unsafe { handlers(event) }.registered = true;
+        let callback = unsafe {
+            function::py_fn_impl(py, &mut METHOD_DEFS[index(event)])
+        };
+        if let Err(err) = register_python_at_fork(py, event, callback) {
+            unsafe { handlers(event) }.registered = false;
+            return Err(err);
+        }
+    }
+    unsafe { handlers(event) }.handlers.push(handler);
+    Ok(())
+}
+
+/// Registers a Python callable to be called when the process forks,
+/// using `os.register_at_fork()`.
+///
+/// Raises `AttributeError` before Python 3.7, which lacks `os.register_at_fork()`.
+pub fn register_python_at_fork<F>(py: Python, event: ForkEvent, callable: F) -> PyResult<()>
+    where F: ::ToPyObject
+{
+    let kwargs = PyDict::new(py);
+    kwargs.set_item(py, keyword(event), callable)?;
+    py.import("os")?.call(py, "register_at_fork", ::NoArgs, Some(&kwargs))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use python::Python;
+    use super::{ForkSafe, ForkEvent, run_handlers};
+
+    #[test]
+    fn test_fork_safe() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let calls = Cell::new(0);
+        let state = ForkSafe::new();
+        assert_eq!(*state.get_or_init(py, |_| { calls.set(calls.get() + 1); 42 }), 42);
+        assert_eq!(*state.get_or_init(py, |_| { calls.set(calls.get() + 1); 43 }), 42);
+        assert_eq!(calls.get(), 1);
+        // pretend the value was inherited from a parent process
+        state.cell.borrow_mut().as_mut().unwrap().0 = 0;
+        assert_eq!(*state.get_or_init(py, |_| { calls.set(calls.get() + 1); 44 }), 44);
+        state.reset(py);
+        assert_eq!(*state.get_or_init(py, |_| 45), 45);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_fork_safe_keeps_first_value() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let state = ForkSafe::new();
+        // as if another thread stored a value while `init` released the GIL
+        let value = state.get_or_init(py, |_| {
+            *state.cell.borrow_mut() = Some((super::current_pid(), 1));
+            2
+        });
+        assert_eq!(*value, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "called recursively")]
+    fn test_fork_safe_recursive() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let state = ForkSafe::new();
+        state.get_or_init(py, |py| *state.get_or_init(py, |_| 1) + 1);
+    }
+
+    #[test]
+    fn test_run_handlers() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static ORDER: AtomicUsize = AtomicUsize::new(0);
+        fn first(_: Python) { ORDER.fetch_add(1, Ordering::SeqCst); }
+        fn second(_: Python) { ORDER.fetch_add(10, Ordering::SeqCst); }
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        if py.import("os").unwrap().get(py, "register_at_fork").is_err() {
+            return; // Python < 3.7
+        }
+        super::register_at_fork(py, ForkEvent::AfterInChild, first).unwrap();
+        super::register_at_fork(py, ForkEvent::AfterInChild, second).unwrap();
+        run_handlers(py, ForkEvent::AfterInChild);
+        assert_eq!(ORDER.load(Ordering::SeqCst), 11);
+    }
+}
@@ -29,7 +29,12 @@ use err::{self, PyResult};
 #[macro_export]
 #[doc(hidden)]
 macro_rules! py_method_def {
-    ($name: expr, $flags: expr, $wrap: expr) => {{
+    ($name: expr, $flags: expr, $wrap: expr) => {
+        py_method_def!($name, $flags, $wrap, "\0")
+    };
+    // `$doc` is a nul-terminated docstring, which can start with a signature
+    // (see `py_text_signature!()`).
+    ($name: expr, $flags: expr, $wrap: expr, $doc: expr) => {{
         static mut METHOD_DEF: $crate::_detail::ffi::PyMethodDef = $crate::_detail::ffi::PyMethodDef {
             //ml_name: bytes!(stringify!($name), "\0"),
             ml_name: 0 as *const $crate::_detail::libc::c_char,
@@ -38,6 +43,7 @@ macro_rules! py_method_def {
             ml_doc: 0 as *const $crate::_detail::libc::c_char
         };
         METHOD_DEF.ml_name = concat!($name, "\0").as_ptr() as *const _;
+        METHOD_DEF.ml_doc = $crate::_detail::method_doc($doc);
         METHOD_DEF.ml_meth = Some(
             ::std::mem::transmute::<$crate::_detail::ffi::PyCFunctionWithKeywords,
                                   $crate::_detail::ffi::PyCFunction>($wrap)
@@ -46,6 +52,104 @@ macro_rules! py_method_def {
     }}
 }
 
+/// Builds the docstring holding the signature of a function, for `inspect.signature()`.
+///
+/// `py_text_signature!(name, { "$self" } [ params ])` expands to a string like
+/// `"name($self, a, b)\n--\n\n\0"`, the format used by CPython's Argument Clinic.
+/// The first parameter (`"$self"`, `"$type"`, or nothing) is the receiver of methods,
+/// and the parameters are in the format produced by `py_argparse_parse_plist!()`.
+/// Default values that are literals are copied into the signature (`true`, `false` and `None`
+/// are written `True`, `False` and `None`). `inspect` rejects the whole signature if a default
+/// isn't a Python constant, so if any other default value is used, this expands to `"\0"`:
+/// the function has no signature.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! py_text_signature {
+    ($name: expr, { } [ $($params:tt)* ]) => {
+        py_text_signature_params!([ $name, "(" ] [ ] $($params)*)
+    };
+    ($name: expr, { $slf:expr } [ $($params:tt)* ]) => {
+        py_text_signature_params!([ $name, "(", $slf ] [ ", " ] $($params)*)
+    };
+}
+
+/// Appends the parameters to the signature built by `py_text_signature!()`;
+/// the second list holds the separator before the next parameter.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! py_text_signature_params {
+    ([ $($out:expr),* ] [ $($sep:expr)* ]) => {
+        concat!($($out,)* ")\n--\n\n\0")
+    };
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {} {true} $rtype:tt ] } $($tail:tt)*) => {
+        py_text_signature_params!([ $($out,)* $($sep,)* stringify!($pname), "=True" ] [ ", " ] $($tail)*)
+    };
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {} {false} $rtype:tt ] } $($tail:tt)*) => {
+        py_text_signature_params!([ $($out,)* $($sep,)* stringify!($pname), "=False" ] [ ", " ] $($tail)*)
+    };
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {} {None} $rtype:tt ] } $($tail:tt)*) => {
+        py_text_signature_params!([ $($out,)* $($sep,)* stringify!($pname), "=None" ] [ ", " ] $($tail)*)
+    };
+    // Other names, and parenthesized or negated non-literals: no signature.
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {} {$default:ident} $rtype:tt ] } $($tail:tt)*) => {
+        "\0"
+    };
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {} {- $default:ident} $rtype:tt ] } $($tail:tt)*) => {
+        "\0"
+    };
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {} {($($default:tt)*)} $rtype:tt ] } $($tail:tt)*) => {
+        "\0"
+    };
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {} {[$($default:tt)*]} $rtype:tt ] } $($tail:tt)*) => {
+        "\0"
+    };
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {} {{$($default:tt)*}} $rtype:tt ] } $($tail:tt)*) => {
+        "\0"
+    };
+    // Literals
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {} {- $default:tt} $rtype:tt ] } $($tail:tt)*) => {
+        py_text_signature_params!([ $($out,)* $($sep,)* stringify!($pname), "=-", stringify!($default) ] [ ", " ] $($tail)*)
+    };
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {} {$default:tt} $rtype:tt ] } $($tail:tt)*) => {
+        py_text_signature_params!([ $($out,)* $($sep,)* stringify!($pname), "=", stringify!($default) ] [ ", " ] $($tail)*)
+    };
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {} {} $rtype:tt ] } $($tail:tt)*) => {
+        py_text_signature_params!([ $($out,)* $($sep,)* stringify!($pname) ] [ ", " ] $($tail)*)
+    };
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {*} {} $rtype:tt ] } $($tail:tt)*) => {
+        py_text_signature_params!([ $($out,)* $($sep,)* "*", stringify!($pname) ] [ ", " ] $($tail)*)
+    };
+    ([ $($out:expr),* ] [ $($sep:expr)* ]
+        { $pname:ident : $ptype:ty = [ {**} {} $rtype:tt ] } $($tail:tt)*) => {
+        py_text_signature_params!([ $($out,)* $($sep,)* "**", stringify!($pname) ] [ ", " ] $($tail)*)
+    };
+}
+
+/// Gets the `ml_doc` of a `PyMethodDef` from a nul-terminated string.
+///
+/// Python 2 doesn't support signatures in docstrings, so this returns NULL for Python 2.
+#[doc(hidden)]
+pub fn method_doc(doc: &'static str) -> *const libc::c_char {
+    debug_assert!(doc.ends_with('\0'));
+    if cfg!(feature="python27-sys") || doc == "\0" {
+        ptr::null()
+    } else {
+        doc.as_ptr() as *const libc::c_char
+    }
+}
+
 /// Creates a Python callable object that invokes a Rust function.
 ///
 /// There are two forms of this macro:
@@ -61,6 +165,10 @@ macro_rules! py_method_def {
 /// the Rust types specified in the parameter list.
 /// See `py_argparse!()` for details on argument parsing.
 ///
+/// On Python 3, the callable has a `__text_signature__`, used by `inspect.signature()`.
+/// Default values that are literals, or `true`, `false` or `None`, are part of the signature;
+/// if any default value is another expression, the callable has no signature.
+///
 /// Form 1:
 ///
 ///  * `py` must be an expression of type `Python`
@@ -130,7 +238,8 @@ macro_rules! py_fn_impl {
         }
         unsafe {
             $crate::_detail::py_fn_impl($py,
-                py_method_def!(stringify!($f), 0, wrap))
+                py_method_def!(stringify!($f), 0, wrap,
+                    py_text_signature!(stringify!($f), { } [ $( { $pname : $ptype = $detail } )* ])))
         }
     }};
     // Form 2: inline function definition
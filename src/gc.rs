@@ -0,0 +1,261 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Control of Python's cyclic garbage collector.
+//!
+//! These functions wrap the `gc` module, e.g. to disable the collector around
+//! latency-sensitive code and to collect explicitly at a convenient time,
+//! or to monitor the collections with callbacks written in Rust.
+//!
+//! ```
+//! use cpython::{gc, Python};
+//!
+//! let gil = Python::acquire_gil();
+//! let py = gil.python();
+//! {
+//!     let _paused = gc::pause(py).unwrap();
+//!     assert!(!gc::is_enabled(py).unwrap());
+//!     // ... allocate many objects without triggering collections ...
+//! }
+//! gc::collect(py);
+//!
+//! # if py.version_info().unwrap().major < 3 { return; }
+//! let callback = gc::add_callback(py, |_py, phase, info| {
+//!     if phase == gc::Phase::Stop {
+//!         println!("generation {}: {} objects collected", info.generation, info.collected);
+//!     }
+//! }).unwrap();
+//! gc::collect(py);
+//! callback.remove(py).unwrap();
+//! ```
+
+use std::cell::Cell;
+use ffi;
+use err::PyResult;
+use objectprotocol::ObjectProtocol;
+use objects::{PyDict, PyModule, PyObject};
+use python::{Python, PythonObject};
+
+fn gc_module(py: Python) -> PyResult<PyModule> {
+    py.import("gc")
+}
+
+/// Enables the automatic garbage collection, like `gc.enable()`.
+pub fn enable(py: Python) -> PyResult<()> {
+    gc_module(py)?.call(py, "enable", ::NoArgs, None)?;
+    Ok(())
+}
+
+/// Disables the automatic garbage collection, like `gc.disable()`.
+///
+/// `pause()` disables it until the end of a scope.
+pub fn disable(py: Python) -> PyResult<()> {
+    gc_module(py)?.call(py, "disable", ::NoArgs, None)?;
+    Ok(())
+}
+
+/// Returns whether the automatic garbage collection is enabled, like `gc.isenabled()`.
+pub fn is_enabled(py: Python) -> PyResult<bool> {
+    gc_module(py)?.call(py, "isenabled", ::NoArgs, None)?.is_true(py)
+}
+
+/// Runs a full collection, and returns the number of unreachable objects found.
+///
+/// Unlike `gc.collect()`, this uses `PyGC_Collect()`, which does nothing if a
+/// collection is already running or if the garbage collector is disabled.
+pub fn collect(_py: Python) -> usize {
+    unsafe { ffi::PyGC_Collect() as usize }
+}
+
+/// Collects the given generation (from 0 to 2), and returns the number of unreachable
+/// objects found, like `gc.collect(generation)`.
+pub fn collect_generation(py: Python, generation: u8) -> PyResult<usize> {
+    gc_module(py)?.call(py, "collect", (generation,), None)?.extract(py)
+}
+
+/// Moves all the objects tracked by the garbage collector to a permanent generation,
+/// which is ignored by the future collections, like `gc.freeze()`.
+///
+/// Raises `AttributeError` before Python 3.7.
+pub fn freeze(py: Python) -> PyResult<()> {
+    gc_module(py)?.call(py, "freeze", ::NoArgs, None)?;
+    Ok(())
+}
+
+/// Moves back the objects of the permanent generation to the oldest generation,
+/// like `gc.unfreeze()`.
+///
+/// Raises `AttributeError` before Python 3.7.
+pub fn unfreeze(py: Python) -> PyResult<()> {
+    gc_module(py)?.call(py, "unfreeze", ::NoArgs, None)?;
+    Ok(())
+}
+
+/// Returns the number of objects in the permanent generation, like `gc.get_freeze_count()`.
+///
+/// Raises `AttributeError` before Python 3.7.
+pub fn freeze_count(py: Python) -> PyResult<usize> {
+    gc_module(py)?.call(py, "get_freeze_count", ::NoArgs, None)?.extract(py)
+}
+
+/// Disables the automatic garbage collection until the returned guard is dropped.
+///
+/// If the garbage collector was already disabled, it stays disabled when the guard is dropped.
+pub fn pause(py: Python) -> PyResult<Paused> {
+    let was_enabled = is_enabled(py)?;
+    if was_enabled {
+        disable(py)?;
+    }
+    Ok(Paused { was_enabled })
+}
+
+/// Returned by `pause()`; enables the garbage collection again when dropped.
+#[must_use]
+pub struct Paused {
+    was_enabled: bool,
+}
+
+impl Drop for Paused {
+    fn drop(&mut self) {
+        if self.was_enabled {
+            let gil = Python::acquire_gil();
+            let py = gil.python();
+            if let Err(e) = enable(py) {
+                e.print(py);
+            }
+        }
+    }
+}
+
+/// The phase of a collection, for the callbacks registered with `add_callback()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// The collection is about to start.
+    Start,
+    /// The collection is finished.
+    Stop,
+}
+
+/// Information about a collection, for the callbacks registered with `add_callback()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CollectionInfo {
+    /// The generation being collected.
+    pub generation: usize,
+    /// The number of objects collected, when the phase is `Stop`.
+    pub collected: usize,
+    /// The number of objects that couldn't be collected and were put in `gc.garbage`,
+    /// when the phase is `Stop`.
+    pub uncollectable: usize,
+}
+
+type CallbackFn = Fn(Python, Phase, &CollectionInfo) + Send;
+
+py_class!(class GcCallback |py| {
+    data callback: Box<CallbackFn>;
+    // Prevents recursive calls, if the callback triggers a collection.
+    data running: Cell<bool>;
+
+    def __call__(&self, phase: String, info: PyDict) -> PyResult<PyObject> {
+        let phase = if phase == "start" { Phase::Start } else { Phase::Stop };
+        let get = |key: &str| -> PyResult<usize> {
+            match info.get_item(py, key) {
+                Some(value) => value.extract(py),
+                None => Ok(0),
+            }
+        };
+        let info = CollectionInfo {
+            generation: get("generation")?,
+            collected: get("collected")?,
+            uncollectable: get("uncollectable")?,
+        };
+        if !self.running(py).replace(true) {
+            (self.callback(py))(py, phase, &info);
+            self.running(py).set(false);
+        }
+        Ok(py.None())
+    }
+});
+
+/// Registers `callback` in `gc.callbacks`, so that it is called at the start and at the end
+/// of each collection.
+///
+/// The callback is registered until `Callback::remove()` is called.
+/// A panic in the callback is reported like an exception raised by a Python callback.
+///
+/// Raises `AttributeError` on Python 2, which doesn't have `gc.callbacks`.
+pub fn add_callback<F>(py: Python, callback: F) -> PyResult<Callback>
+    where F: Fn(Python, Phase, &CollectionInfo) + Send + 'static
+{
+    let callback = GcCallback::create_instance(py, Box::new(callback), Cell::new(false))?;
+    gc_module(py)?.get(py, "callbacks")?.call_method(py, "append", (&callback,), None)?;
+    Ok(Callback(callback))
+}
+
+/// A callback registered with `add_callback()`.
+pub struct Callback(GcCallback);
+
+impl Callback {
+    /// Removes the callback from `gc.callbacks`.
+    pub fn remove(self, py: Python) -> PyResult<()> {
+        gc_module(py)?.get(py, "callbacks")?.call_method(py, "remove", (self.0,), None)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use python::Python;
+    use super::*;
+
+    #[test]
+    fn test_pause() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        assert!(is_enabled(py).unwrap());
+        {
+            let _paused = pause(py).unwrap();
+            assert!(!is_enabled(py).unwrap());
+            {
+                let _paused = pause(py).unwrap();
+            }
+            assert!(!is_enabled(py).unwrap());
+        }
+        assert!(is_enabled(py).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature="python3-sys")]
+    fn test_callback() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let phases = Arc::new(Mutex::new(Vec::new()));
+        let phases2 = phases.clone();
+        let callback = add_callback(py, move |_py, phase, info| {
+            phases2.lock().unwrap().push((phase, info.generation));
+        }).unwrap();
+        collect_generation(py, 1).unwrap();
+        callback.remove(py).unwrap();
+        let count = phases.lock().unwrap().len();
+        collect_generation(py, 1).unwrap();
+        let phases = phases.lock().unwrap();
+        // Other collections can happen automatically.
+        assert_eq!(phases.len(), count);
+        assert!(phases.windows(2).any(|w| w == [(Phase::Start, 1), (Phase::Stop, 1)]));
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Assertions checking that the GIL is held by the current thread.
+//!
+//! With the `debug-gil` feature, the functions creating `Python` tokens and
+//! the entry points from Python (callbacks, slots, `py_fn!()` wrappers, ...)
+//! check that the GIL is held by the current thread and that the interpreter
+//! is in a usable state. A misuse of `Python::assume_gil_acquired()` (or of
+//! the raw FFI) then panics immediately, instead of corrupting the interpreter.
+//!
+//! Without the feature, the checks compile to nothing.
+
+#[cfg(feature="debug-gil")]
+use ffi;
+
+/// Returns whether the current thread holds the GIL.
+#[cfg(all(feature="debug-gil", feature="python3-sys", Py_3_4))]
+fn gil_held() -> bool {
+    // PyGILState_Check() returns 1 before the initialization of the interpreter.
+    unsafe { ffi::PyGILState_Check() != 0 && !ffi::PyGILState_GetThisThreadState().is_null() }
+}
+
+// PyGILState_Check() is new in Python 3.4. Before, PyThreadState_GET() is a fatal error
+// when no thread holds the GIL, instead of a panic.
+#[cfg(all(feature="debug-gil", any(feature="python27-sys", all(feature="python3-sys", not(Py_3_4)))))]
+fn gil_held() -> bool {
+    unsafe {
+        let tstate = ffi::PyGILState_GetThisThreadState();
+        !tstate.is_null() && tstate == ffi::PyThreadState_GET()
+    }
+}
+
+/// Panics if the current thread doesn't hold the GIL.
+#[cfg(feature="debug-gil")]
+#[doc(hidden)]
+pub fn assert_gil_held() {
+    if !gil_held() {
+        panic!("Python API used without holding the GIL \
+                (was Python::assume_gil_acquired() called without the GIL?)");
+    }
+}
+
+#[cfg(not(feature="debug-gil"))]
+#[doc(hidden)]
+#[inline(always)]
+pub fn assert_gil_held() {}
+
+/// Panics if the current thread can't acquire the GIL, because the interpreter
+/// is being finalized (`Py_IsInitialized()` is false from the start of the finalization).
+///
+/// Acquiring the GIL is still allowed on the thread finalizing the interpreter,
+/// which already holds it (e.g. to drop objects).
+#[cfg(feature="debug-gil")]
+pub fn assert_can_acquire_gil() {
+    if unsafe { ffi::Py_IsInitialized() } == 0 && !gil_held() {
+        panic!("cannot acquire the GIL while the Python interpreter is being finalized");
+    }
+}
+
+#[cfg(not(feature="debug-gil"))]
+#[inline(always)]
+pub fn assert_can_acquire_gil() {}
+
+#[cfg(all(test, feature="debug-gil"))]
+mod test {
+    use std::thread;
+    use python::Python;
+    use super::*;
+
+    #[test]
+    #[cfg(any(feature="python27-sys", Py_3_4))]
+    fn test_gil_held() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        assert_gil_held();
+        // Drop the GIL, then try to use it from a thread not holding it.
+        py.allow_threads(|| {
+            let result = thread::spawn(|| {
+                let _py = unsafe { Python::assume_gil_acquired() };
+            }).join();
+            assert!(result.is_err());
+        });
+        assert_gil_held();
+    }
+}
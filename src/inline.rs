@@ -0,0 +1,160 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Support for the `python!` macro.
+
+use err::{PyErr, PyResult};
+use exc;
+use conversion::FromPyObject;
+use objects::{PyDict, PyObject};
+use python::Python;
+
+/// Runs the code of a `python!` block, with `variables` as globals.
+#[doc(hidden)]
+pub fn run_inline(py: Python, code: &str, variables: Vec<(&'static str, PyObject)>) -> PyResult<PyDict> {
+    let globals = PyDict::new(py);
+    // Python 2 doesn't add the builtins to a globals dict that lacks them.
+    globals.set_item(py, "__builtins__", py.builtins()?)?;
+    for (name, value) in variables {
+        globals.set_item(py, name, value)?;
+    }
+    py.run(&dedent(py, code)?, Some(&globals), None)?;
+    Ok(globals)
+}
+
+/// Removes the common leading indentation of the lines of `code`, so that Python code
+/// embedded in Rust can be indented like the surrounding Rust code.
+pub fn dedent(py: Python, code: &str) -> PyResult<String> {
+    py.import("textwrap")?.call(py, "dedent", (code,), None)?.extract(py)
+}
+
+/// Gets the output `name` of a `python!` block.
+#[doc(hidden)]
+pub fn inline_output<T>(py: Python, globals: &PyDict, name: &str) -> PyResult<T>
+    where T: for<'s> FromPyObject<'s>
+{
+    match globals.get_item(py, name) {
+        Some(value) => value.extract(py),
+        None => Err(PyErr::new::<exc::NameError, _>(py,
+            format!("name '{}' is not defined by the python! block", name))),
+    }
+}
+
+/// Runs a block of Python code, with Rust variables as inputs and outputs.
+///
+/// Syntax: `python!(py, { inputs } "code" => outputs)`, where:
+///
+/// * `inputs` is a comma-separated list of the Rust variables (e.g. `x`) or named expressions
+///   (e.g. `total = a + b`) accessible to the code, which are converted with `ToPyObject`.
+///   The braces can be omitted if there are no inputs.
+/// * `code` is a string containing Python statements. Common leading indentation is removed,
+///   so the code can be indented to match the surrounding Rust code.
+/// * `=> outputs` is optional; it is either `name: Type`, or a tuple `(name: Type, ...)`.
+///   After running the code, each output variable is extracted as the given Rust type.
+///
+/// The macro evaluates to a `PyResult` of the outputs (or `()` if there are none).
+/// The code is run with a new dict as globals, containing only the inputs and `__builtins__`.
+///
+/// ```
+/// #[macro_use] extern crate cpython;
+/// use cpython::Python;
+///
+/// fn main() {
+///     let gil = Python::acquire_gil();
+///     let py = gil.python();
+///     let words = vec!["apple", "banana", "cherry"];
+///     let (longest, count): (String, usize) = python!(py, { words, min_len = 5 } "
+///         selected = [w for w in words if len(w) > min_len]
+///         longest = max(selected, key=len)
+///         count = len(selected)
+///     " => (longest: String, count: usize)).unwrap();
+///     assert_eq!((&longest[..], count), ("banana", 2));
+/// }
+/// ```
+#[macro_export]
+macro_rules! python {
+    ($py:expr, { $($inputs:tt)* } $code:expr => ( $($output:ident : $ty:ty),* $(,)* )) => {
+        python!(@run $py, { $($inputs)* } $code).and_then(|globals| {
+            let py = $py;
+            Ok(( $( $crate::_detail::inline_output::<$ty>(py, &globals, stringify!($output))?, )* ))
+        })
+    };
+    ($py:expr, { $($inputs:tt)* } $code:expr => $output:ident : $ty:ty) => {
+        python!(@run $py, { $($inputs)* } $code).and_then(|globals| {
+            $crate::_detail::inline_output::<$ty>($py, &globals, stringify!($output))
+        })
+    };
+    ($py:expr, { $($inputs:tt)* } $code:expr) => {
+        python!(@run $py, { $($inputs)* } $code).map(|_| ())
+    };
+    ($py:expr, $code:expr => $($outputs:tt)*) => {
+        python!($py, { } $code => $($outputs)*)
+    };
+    ($py:expr, $code:expr) => {
+        python!($py, { } $code)
+    };
+    (@run $py:expr, { $($inputs:tt)* } $code:expr) => {{
+        let py = $py;
+        let mut variables = Vec::new();
+        python!(@inputs py, variables, $($inputs)*);
+        $crate::_detail::run_inline(py, $code, variables)
+    }};
+    (@inputs $py:ident, $vars:ident, ) => {};
+    (@inputs $py:ident, $vars:ident, $name:ident = $value:expr, $($rest:tt)*) => {
+        python!(@inputs $py, $vars, $name = $value);
+        python!(@inputs $py, $vars, $($rest)*);
+    };
+    (@inputs $py:ident, $vars:ident, $name:ident = $value:expr) => {
+        $vars.push((stringify!($name),
+            $crate::PythonObject::into_object($crate::ToPyObject::to_py_object(&$value, $py))));
+    };
+    (@inputs $py:ident, $vars:ident, $name:ident, $($rest:tt)*) => {
+        python!(@inputs $py, $vars, $name = $name);
+        python!(@inputs $py, $vars, $($rest)*);
+    };
+    (@inputs $py:ident, $vars:ident, $name:ident) => {
+        python!(@inputs $py, $vars, $name = $name);
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use python::Python;
+    use exc;
+
+    #[test]
+    fn test_python_macro() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let x = 20;
+        python!(py, { x } "assert x == 20").unwrap();
+        python!(py, "assert 'x' not in globals()").unwrap();
+        let y: i32 = python!(py, { x, z = x + 1, } "y = x + z" => y: i32).unwrap();
+        assert_eq!(y, 41);
+        let (a, b) = python!(py, "
+            a = 'text'
+            b = [1, 2]
+        " => (a: String, b: Vec<i32>)).unwrap();
+        assert_eq!((&a[..], b), ("text", vec![1, 2]));
+
+        let err = python!(py, "a = 1" => b: i32).unwrap_err();
+        assert!(err.matches(py, py.get_type::<exc::NameError>()));
+        let err = python!(py, { x } "x.missing").unwrap_err();
+        assert!(err.matches(py, py.get_type::<exc::AttributeError>()));
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Sharing `py_class!` instances between independently compiled extension modules.
+//!
+//! Two extension modules can't downcast each other's `py_class!` instances with `cast_as()`:
+//! each module has its own copy of the type object, and the layout of the instances depends
+//! on the compiler and on the versions of the crates used to build each module.
+//!
+//! Instead, the module defining a class registers a table of `extern "C"` functions
+//! (an interface) for its type, and other modules look that table up for the objects
+//! they receive. The interface only depends on the definition of the table, which is
+//! typically shared in a small crate used by both modules.
+//!
+//! The registry is stored in the interpreter (so it is shared by all extension modules),
+//! as capsules named after the interfaces.
+//!
+//! ```
+//! extern crate cpython;
+//! #[cfg(feature="python27-sys")]
+//! extern crate python27_sys as ffi;
+//! #[cfg(feature="python3-sys")]
+//! extern crate python3_sys as ffi;
+//! # fn main() {
+//! use cpython::{Python, PyType};
+//! use cpython::interface::{self, Interface};
+//!
+//! // In a crate shared by both extension modules:
+//! #[repr(C)]
+//! pub struct Counter {
+//!     pub get: unsafe extern "C" fn(obj: *mut ffi::PyObject) -> u64,
+//! }
+//!
+//! unsafe impl Interface for Counter {
+//!     const NAME: &'static str = "example.Counter.v1";
+//! }
+//!
+//! // In the extension module defining the class:
+//! unsafe extern "C" fn get(_obj: *mut ffi::PyObject) -> u64 {
+//!     // e.g. `PyObject::from_borrowed_ptr(py, obj).unchecked_cast_into::<MyClass>()`
+//!     42
+//! }
+//!
+//! static COUNTER: Counter = Counter { get };
+//!
+//! let gil = Python::acquire_gil();
+//! let py = gil.python();
+//! let ty = py.eval("type('MyClass', (), {})", None, None).unwrap().cast_into::<PyType>(py).unwrap();
+//! interface::register(py, &ty, &COUNTER).unwrap();
+//!
+//! // In another extension module:
+//! let obj = ty.call(py, cpython::NoArgs, None).unwrap();
+//! let counter = interface::downcast::<Counter>(py, obj).unwrap();
+//! assert_eq!(unsafe { (counter.interface().get)(counter.as_ptr()) }, 42);
+//! # }
+//! ```
+
+use std::ffi::CString;
+use libc::c_void;
+use ffi;
+use err::{PyErr, PyResult};
+use exc;
+use objects::{PyObject, PyDict, PyModule, PyTuple, PyType, PyCapsule};
+use objectprotocol::ObjectProtocol;
+use python::{Python, PythonObject, ToPythonPointer};
+
+/// The name of the module holding the registry, in `sys.modules`.
+/// The suffix is the version of the registry format.
+const REGISTRY_MODULE: &str = "_rust_cpython_interfaces_v1";
+
+/// A table of functions that can be registered for a Python type.
+///
+/// # Safety
+/// The type must be `#[repr(C)]`, and only contain `extern "C"` function pointers and
+/// other FFI-safe values. Its definition must be the same in all the modules using `NAME`:
+/// a change in the definition requires a new name (e.g. with a new version suffix).
+pub unsafe trait Interface: Sync + 'static {
+    /// The unique name of the interface, e.g. `"mycrate.Shape.v1"`.
+    const NAME: &'static str;
+}
+
+fn registry(py: Python) -> PyResult<PyDict> {
+    let modules = py.import("sys")?.get(py, "modules")?;
+    if let Ok(module) = modules.get_item(py, REGISTRY_MODULE) {
+        return Ok(module.getattr(py, "registry")?.cast_into::<PyDict>(py)?);
+    }
+    let module = PyModule::new(py, REGISTRY_MODULE)?;
+    let registry = PyDict::new(py);
+    module.add(py, "registry", &registry)?;
+    modules.set_item(py, REGISTRY_MODULE, module)?;
+    Ok(registry)
+}
+
+/// Registers the implementation `interface` of `I` for the instances of `ty`, including
+/// the instances of its subclasses.
+///
+/// If an implementation of `I` was already registered for `ty`, it is replaced.
+pub fn register<I: Interface>(py: Python, ty: &PyType, interface: &'static I) -> PyResult<()> {
+    let capsule = match PyCapsule::new(py, interface as *const I as *mut c_void, I::NAME) {
+        Ok(capsule) => capsule,
+        Err(_) => return Err(PyErr::new::<exc::ValueError, _>(py, "interface name contains a nul byte")),
+    };
+    registry(py)?.set_item(py, (ty, I::NAME), capsule)
+}
+
+/// Looks up the implementation of `I` for the type of `obj`.
+///
+/// Returns `None` if no implementation was registered for the type of `obj`, or for any of
+/// its base classes.
+pub fn lookup<I: Interface>(py: Python, obj: &PyObject) -> PyResult<Option<&'static I>> {
+    let registry = registry(py)?;
+    let name = match CString::new(I::NAME) {
+        Ok(name) => name,
+        Err(_) => return Err(PyErr::new::<exc::ValueError, _>(py, "interface name contains a nul byte")),
+    };
+    let mro = obj.get_type(py).as_object().getattr(py, "__mro__")?.cast_into::<PyTuple>(py)?;
+    for ty in mro.iter(py) {
+        if let Some(capsule) = registry.get_item(py, (ty, I::NAME)) {
+            unsafe {
+                // The capsule name is checked, in case the registry was modified from Python.
+                let ptr = ffi::PyCapsule_GetPointer(capsule.as_ptr(), name.as_ptr());
+                if ptr.is_null() {
+                    return Err(PyErr::fetch(py));
+                }
+                return Ok(Some(&*(ptr as *const I)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// A Python object together with its implementation of the interface `I`.
+pub struct Shared<I: 'static> {
+    obj: PyObject,
+    interface: &'static I,
+}
+
+impl <I: 'static> Shared<I> {
+    /// Gets the implementation of `I` for the object.
+    pub fn interface(&self) -> &'static I {
+        self.interface
+    }
+
+    /// Gets the object, to be passed to the functions of the interface.
+    pub fn as_ptr(&self) -> *mut ffi::PyObject {
+        self.obj.as_ptr()
+    }
+
+    /// Gets the object.
+    pub fn as_object(&self) -> &PyObject {
+        &self.obj
+    }
+
+    /// Gets the object.
+    pub fn into_object(self) -> PyObject {
+        self.obj
+    }
+}
+
+/// Gets `obj` together with its implementation of `I`.
+///
+/// Raises `TypeError` if there is no implementation of `I` for `obj`.
+pub fn downcast<I: Interface>(py: Python, obj: PyObject) -> PyResult<Shared<I>> {
+    match lookup::<I>(py, &obj)? {
+        Some(interface) => Ok(Shared { obj, interface }),
+        None => {
+            Err(PyErr::new::<exc::TypeError, _>(py,
+                format!("'{}' object does not implement {}", obj.get_type(py).name(py), I::NAME)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ffi;
+    use python::Python;
+    use objects::{PyDict, PyObject, PyType};
+    use objectprotocol::ObjectProtocol;
+    use super::*;
+
+    #[repr(C)]
+    struct Size {
+        size: unsafe extern "C" fn(*mut ffi::PyObject) -> ffi::Py_ssize_t,
+    }
+
+    unsafe impl Interface for Size {
+        const NAME: &'static str = "test.Size.v1";
+    }
+
+    #[repr(C)]
+    struct Other {
+        value: u32,
+    }
+
+    unsafe impl Interface for Other {
+        const NAME: &'static str = "test.Other.v1";
+    }
+
+    unsafe extern "C" fn size(obj: *mut ffi::PyObject) -> ffi::Py_ssize_t {
+        ffi::PyObject_Size(obj)
+    }
+
+    static SIZE: Size = Size { size };
+
+    #[test]
+    fn test_registry() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let d = PyDict::new(py);
+        py.run("class Base(list): pass\nclass Derived(Base): pass", None, Some(&d)).unwrap();
+        let base = d.get_item(py, "Base").unwrap().cast_into::<PyType>(py).unwrap();
+        register(py, &base, &SIZE).unwrap();
+
+        let obj = py.eval("Derived([1, 2, 3])", None, Some(&d)).unwrap();
+        assert!(lookup::<Other>(py, &obj).unwrap().is_none());
+        let shared = downcast::<Size>(py, obj).unwrap();
+        assert_eq!(unsafe { (shared.interface().size)(shared.as_ptr()) }, 3);
+
+        let obj: PyObject = py.eval("[1, 2]", None, None).unwrap();
+        assert!(downcast::<Size>(py, obj).is_err());
+    }
+}
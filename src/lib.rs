@@ -19,6 +19,8 @@
 #![cfg_attr(feature="nightly", feature(
     const_fn, // for GILProtected::new (#24111)
     specialization, // for impl FromPyObject<'source> for Vec<...> (#31844)
+    inclusive_range, inclusive_range_syntax, // for impl SliceBounds for RangeInclusive (#28237)
+    core_intrinsics, // for the Rust type names in extraction errors
 ))]
 
 #![allow(unused_imports)] // because some imports are only necessary with python 2.x or 3.x
@@ -99,11 +101,16 @@ extern crate python3_sys as ffi;
 pub use ffi::Py_ssize_t;
 pub use err::{PyErr, PyResult};
 pub use objects::*;
-pub use python::{Python, PythonObject, PythonObjectWithCheckedDowncast, PythonObjectDowncastError, PythonObjectWithTypeObject, PyClone, PyDrop};
+pub use python::{Python, PythonObject, PythonObjectWithCheckedDowncast, PythonObjectDowncastError, PythonObjectWithTypeObject, PyClone, PyDrop, VersionInfo};
 pub use pythonrun::{GILGuard, GILProtected, prepare_freethreaded_python};
 pub use conversion::{FromPyObject, RefFromPyObject, ToPyObject};
 pub use py_class::{CompareOp};
-pub use objectprotocol::{ObjectProtocol};
+pub use objectprotocol::{ObjectProtocol, Attributes};
+pub use sharedref::{PySharedRefCell, PySharedRef, PyLeaked, PyLeakedRef, PyLeakedRefMut};
+pub use pybox::PyBox;
+pub use cell::PyRefCell;
+pub use context::ContextGuard;
+pub use weakref::PyWeak;
 
 #[cfg(feature="python27-sys")]
 #[allow(non_camel_case_types)]
@@ -190,11 +197,36 @@ mod conversion;
 mod objects;
 mod objectprotocol;
 mod pythonrun;
-pub mod argparse;
-mod function;
+mod sharedref;
+mod weakref;
+mod context;
+mod gilcheck;
+#[macro_use] mod inline;
+#[macro_use] mod union;
+#[macro_use] pub mod argparse;
+#[macro_use] mod function;
 pub mod buffer;
+pub mod cffi;
+pub mod ctypes;
+pub mod cython;
+pub mod dataclass;
+pub mod display;
+pub mod dlpack;
+pub mod fork;
+pub mod interface;
+pub mod marshal;
+pub mod pickle;
+pub mod testing;
+#[cfg(feature="log")]
+pub mod logging;
+#[cfg(feature="tracing")]
+pub mod tracing;
 //pub mod rustobject;
-pub mod py_class;
+#[macro_use] pub mod py_class;
+mod pybox;
+mod cell;
+pub mod gc;
+pub mod views;
 
 /// Private re-exports for macros. Do not use.
 #[doc(hidden)]
@@ -206,8 +238,10 @@ pub mod _detail {
         pub use ::libc::{c_char, c_void, c_int};
     }
     pub use err::{from_owned_ptr_or_panic, result_from_owned_ptr};
-    pub use function::{handle_callback, py_fn_impl, AbortOnDrop,
+    pub use function::{handle_callback, py_fn_impl, method_doc, AbortOnDrop,
         PyObjectCallbackConverter, PythonObjectCallbackConverter};
+    pub use inline::{run_inline, inline_output};
+    pub use union::UnionErrors;
 }
 
 /// Expands to an `extern "C"` function that allows Python to load
@@ -229,7 +263,7 @@ pub mod _detail {
 ///
 /// py_module_initializer!(hello, inithello, PyInit_hello, |py, m| {
 ///     m.add(py, "__doc__", "Module documentation string")?;
-///     m.add(py, "run", py_fn!(py, run()))?;
+///     m.add_function(py, "run", py_fn!(py, run()))?;
 ///     Ok(())
 /// });
 ///
@@ -0,0 +1,170 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Forwarding of [`log`](https://docs.rs/log) records to the Python `logging` module.
+//!
+//! This module requires the `log` feature.
+//!
+//! An extension module calls `cpython::logging::init()` in its initializer;
+//! from then on, the records emitted using the `log` macros are handled by the
+//! logging configuration of the host application:
+//!
+//! ```ignore
+//! py_module_initializer!(hello, inithello, PyInit_hello, |py, m| {
+//!     cpython::logging::init().ok();
+//!     ...
+//! });
+//! ```
+//!
+//! The logger name is the record's target (by default, the module path), with `::`
+//! replaced by `.`: a record logged in `hello::parser` goes to the Python logger
+//! `hello.parser`.
+//! Rust log levels map to the Python levels of the same name; `Trace` maps to level 5,
+//! which is below `logging.DEBUG`.
+//!
+//! The GIL is acquired only when a record is emitted, so logging from a thread that
+//! does not hold the GIL is fine.
+
+extern crate log;
+
+use self::log::{Log, Level, LevelFilter, Metadata, Record, SetLoggerError};
+use err::PyResult;
+use objectprotocol::ObjectProtocol;
+use python::Python;
+
+/// A `log::Log` implementation forwarding records to the Python `logging` module.
+pub struct PythonLogger;
+
+static LOGGER: PythonLogger = PythonLogger;
+
+/// Installs `PythonLogger` as the logger used by the `log` crate.
+///
+/// All levels are passed on to Python, which filters the records according to
+/// its own configuration.
+///
+/// Fails if a logger was already installed.
+pub fn init() -> Result<(), SetLoggerError> {
+    init_with_level(LevelFilter::Trace)
+}
+
+/// Installs `PythonLogger` as the logger used by the `log` crate, discarding
+/// the records with a level more verbose than `level` without acquiring the GIL.
+///
+/// Fails if a logger was already installed.
+pub fn init_with_level(level: LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_logger(&LOGGER)?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+/// Gets the Python logging level corresponding to a Rust log level.
+pub fn python_level(level: Level) -> i32 {
+    match level {
+        Level::Error => 40,
+        Level::Warn => 30,
+        Level::Info => 20,
+        Level::Debug => 10,
+        Level::Trace => 5,
+    }
+}
+
+/// Gets the name of the Python logger used for the given log target.
+pub fn logger_name(target: &str) -> String {
+    target.replace("::", ".")
+}
+
+impl PythonLogger {
+    fn emit(&self, py: Python, record: &Record) -> PyResult<()> {
+        let name = logger_name(record.target());
+        let level = python_level(record.level());
+        let logger = py.import("logging")?.call(py, "getLogger", (&name,), None)?;
+        if !logger.call_method(py, "isEnabledFor", (level,), None)?.is_true(py)? {
+            return Ok(());
+        }
+        let msg = format!("{}", record.args());
+        let pathname = record.file().unwrap_or("<unknown>");
+        let lineno = record.line().unwrap_or(0);
+        let py_record = logger.call_method(py, "makeRecord",
+            (&name, level, pathname, lineno, msg, ::NoArgs, py.None()), None)?;
+        logger.call_method(py, "handle", (py_record,), None)?;
+        Ok(())
+    }
+}
+
+impl Log for PythonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        if let Err(e) = self.emit(py, record) {
+            // There's no caller to report the error to
+            e.print(py);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::log::{Level, Log, Record};
+    use objects::PyDict;
+    use python::Python;
+    use super::PythonLogger;
+
+    #[test]
+    fn test_forward_record() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let d = PyDict::new(py);
+        py.run("import logging\n\
+                class H(logging.Handler):\n    \
+                    records = []\n    \
+                    def emit(self, record):\n        \
+                        self.records.append(record)\n\
+                logger = logging.getLogger('cpython_test.logging')\n\
+                logger.setLevel(logging.INFO)\n\
+                logger.addHandler(H())\n", Some(&d), None).unwrap();
+
+        PythonLogger.emit(py, &Record::builder()
+            .args(format_args!("hello {}", 42))
+            .level(Level::Warn)
+            .target("cpython_test::logging")
+            .file(Some("src/logging.rs"))
+            .line(Some(7))
+            .build()).unwrap();
+        // below the level of the Python logger
+        PythonLogger.emit(py, &Record::builder()
+            .args(format_args!("ignored"))
+            .level(Level::Debug)
+            .target("cpython_test::logging")
+            .build()).unwrap();
+        PythonLogger.flush();
+
+        let r: (usize, String, String, i32) = py.eval(
+            "(len(H.records), H.records[0].getMessage(), H.records[0].levelname, H.records[0].lineno)",
+            Some(&d), None).unwrap().extract(py).unwrap();
+        assert_eq!(r, (1, "hello 42".to_string(), "WARNING".to_string(), 7));
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Serialization of Python values in the internal format of the `marshal` module.
+//!
+//! The main use of marshal is to store compiled code objects, the way Python does in `.pyc`
+//! files. The format depends on the Python version: only load data written by the same
+//! interpreter version.
+//!
+//! ```
+//! use cpython::{Python, PyDict};
+//! use cpython::marshal;
+//!
+//! let gil = Python::acquire_gil();
+//! let py = gil.python();
+//!
+//! let code = py.eval("compile('x = 6 * 7', 'plugin.py', 'exec')", None, None).unwrap();
+//! let data = marshal::dumps(py, &code).unwrap();
+//! // data can be written to disk, and loaded at the next startup:
+//! let code = marshal::loads(py, &data).unwrap();
+//!
+//! let globals = PyDict::new(py);
+//! // Older Python versions don't add `__builtins__` to the globals.
+//! globals.set_item(py, "__builtins__", py.builtins().unwrap()).unwrap();
+//! globals.set_item(py, "code", code).unwrap();
+//! py.run("exec(code)", Some(&globals), None).unwrap();
+//! assert_eq!(globals.get_item(py, "x").unwrap().extract::<i32>(py).unwrap(), 42);
+//! ```
+
+use libc::c_int;
+use ffi;
+use err::{self, PyResult};
+use objects::{PyObject, PyBytes};
+use python::{Python, PythonObject};
+
+/// The current version of the marshal format (`marshal.version`).
+pub const VERSION: c_int = ffi::marshal::Py_MARSHAL_VERSION;
+
+/// Serializes `obj` using the current version of the marshal format.
+///
+/// Raises `ValueError` if `obj` contains values that are not supported by marshal.
+pub fn dumps(py: Python, obj: &PyObject) -> PyResult<Vec<u8>> {
+    dumps_version(py, obj, VERSION)
+}
+
+/// Serializes `obj` using the given version of the marshal format.
+pub fn dumps_version(py: Python, obj: &PyObject, version: c_int) -> PyResult<Vec<u8>> {
+    let bytes: PyBytes = unsafe {
+        err::result_cast_from_owned_ptr(py,
+            ffi::marshal::PyMarshal_WriteObjectToString(obj.as_ptr(), version))?
+    };
+    Ok(bytes.data(py).to_vec())
+}
+
+/// Deserializes a value serialized in the marshal format.
+///
+/// Raises `EOFError`, `ValueError` or `TypeError` if the data is invalid.
+pub fn loads(py: Python, data: &[u8]) -> PyResult<PyObject> {
+    unsafe {
+        err::result_from_owned_ptr(py,
+            ffi::marshal::PyMarshal_ReadObjectFromString(
+                data.as_ptr() as *mut _, data.len() as ffi::Py_ssize_t))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use python::Python;
+    use objectprotocol::ObjectProtocol;
+    use super::{dumps, loads};
+
+    #[test]
+    fn test_roundtrip() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let obj = py.eval("(1, 2.5, 'str', b'bytes', [None, True], {'a': frozenset([1])})", None, None).unwrap();
+        let data = dumps(py, &obj).unwrap();
+        let copy = loads(py, &data).unwrap();
+        assert!(copy.rich_compare(py, &obj, ::CompareOp::Eq).unwrap().is_true(py).unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let obj = py.eval("object()", None, None).unwrap();
+        assert!(dumps(py, &obj).is_err());
+        assert!(loads(py, b"").is_err());
+        assert!(loads(py, b"\xff garbage").is_err());
+    }
+}
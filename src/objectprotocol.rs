@@ -20,9 +20,9 @@ use std::fmt;
 use std::cmp::Ordering;
 use ffi;
 use libc;
-use python::{Python, PythonObject, ToPythonPointer};
-use objects::{PyObject, PyTuple, PyDict, PyString};
-use conversion::ToPyObject;
+use python::{Python, PythonObject, ToPythonPointer, PyClone};
+use objects::{PyObject, PyTuple, PyDict, PyList, PyString};
+use conversion::{FromPyObject, ToPyObject};
 use err::{PyErr, PyResult, self};
 
 /// Trait that contains methods
@@ -46,6 +46,16 @@ pub trait ObjectProtocol : PythonObject {
         })
     }
 
+    /// Retrieves an attribute value and extracts it as a `T`.
+    ///
+    /// If the extraction fails with `TypeError`, the error message names the attribute.
+    #[inline]
+    fn getattr_as<T>(&self, py: Python, attr_name: &str) -> PyResult<T> where T: for<'s> FromPyObject<'s> {
+        extract_as(py, &self.getattr(py, attr_name)?, || {
+            Some(format!("attribute '{}' of '{}' object", attr_name, self.as_object().get_type(py).name(py)))
+        })
+    }
+
     /// Sets an attribute value.
     /// This is equivalent to the Python expression 'self.attr_name = value'.
     #[inline]
@@ -69,6 +79,30 @@ pub trait ObjectProtocol : PythonObject {
         })
     }
 
+    /// Returns the sorted list of the attribute names of the object.
+    /// This is equivalent to the Python expression 'dir(self)'.
+    #[inline]
+    fn dir(&self, py: Python) -> PyResult<PyList> {
+        unsafe {
+            err::result_cast_from_owned_ptr(py, ffi::PyObject_Dir(self.as_ptr()))
+        }
+    }
+
+    /// Returns an iterator over the `(name, value)` pairs of the attributes listed by `dir()`.
+    ///
+    /// Each value is looked up when the iterator reaches it; if the lookup fails
+    /// (e.g. for a property raising an exception), the iterator yields the error
+    /// and continues with the next attribute.
+    #[inline]
+    fn attributes<'p>(&self, py: Python<'p>) -> PyResult<Attributes<'p>> {
+        Ok(Attributes {
+            py,
+            obj: self.as_object().clone_ref(py),
+            names: self.dir(py)?,
+            index: 0,
+        })
+    }
+
     /// Compares two Python objects.
     ///
     /// On Python 2, this is equivalent to the Python expression 'cmp(self, other)'.
@@ -142,6 +176,60 @@ pub trait ObjectProtocol : PythonObject {
         })
     }
 
+    /// Compares two Python objects, and returns the result as a Rust `bool`.
+    ///
+    /// Like `rich_compare()`, but the result of the comparison is converted with `is_true()`.
+    /// As in Python, identical objects are always equal (and never unequal).
+    fn rich_compare_bool<O>(&self, py: Python, other: O, compare_op: ::CompareOp) -> PyResult<bool> where O: ToPyObject {
+        other.with_borrowed_ptr(py, |other| unsafe {
+            let v = ffi::PyObject_RichCompareBool(self.as_ptr(), other, compare_op as libc::c_int);
+            if v == -1 {
+                Err(PyErr::fetch(py))
+            } else {
+                Ok(v != 0)
+            }
+        })
+    }
+
+    /// This is equivalent to the Python expression 'self < other', converted to a `bool`.
+    #[inline]
+    fn lt<O>(&self, py: Python, other: O) -> PyResult<bool> where O: ToPyObject {
+        self.rich_compare_bool(py, other, ::CompareOp::Lt)
+    }
+
+    /// This is equivalent to the Python expression 'self <= other', converted to a `bool`.
+    #[inline]
+    fn le<O>(&self, py: Python, other: O) -> PyResult<bool> where O: ToPyObject {
+        self.rich_compare_bool(py, other, ::CompareOp::Le)
+    }
+
+    /// This is equivalent to the Python expression 'self > other', converted to a `bool`.
+    #[inline]
+    fn gt<O>(&self, py: Python, other: O) -> PyResult<bool> where O: ToPyObject {
+        self.rich_compare_bool(py, other, ::CompareOp::Gt)
+    }
+
+    /// This is equivalent to the Python expression 'self >= other', converted to a `bool`.
+    #[inline]
+    fn ge<O>(&self, py: Python, other: O) -> PyResult<bool> where O: ToPyObject {
+        self.rich_compare_bool(py, other, ::CompareOp::Ge)
+    }
+
+    /// This is equivalent to the Python expression 'self == other', converted to a `bool`.
+    ///
+    /// It is not named `eq()` so that it doesn't clash with `PartialEq::eq()`:
+    /// the `==` operator on `PyObject` compares the identity of the objects, not their value.
+    #[inline]
+    fn rich_eq<O>(&self, py: Python, other: O) -> PyResult<bool> where O: ToPyObject {
+        self.rich_compare_bool(py, other, ::CompareOp::Eq)
+    }
+
+    /// This is equivalent to the Python expression 'self != other', converted to a `bool`.
+    #[inline]
+    fn rich_ne<O>(&self, py: Python, other: O) -> PyResult<bool> where O: ToPyObject {
+        self.rich_compare_bool(py, other, ::CompareOp::Ne)
+    }
+
     /// Compute the string representation of self.
     /// This is equivalent to the Python expression 'repr(self)'.
     #[inline]
@@ -221,6 +309,23 @@ pub trait ObjectProtocol : PythonObject {
         self.getattr(py, name)?.call(py, args, kwargs)
     }
 
+    /// Calls the object and extracts the result as a `T`.
+    ///
+    /// If the extraction fails with `TypeError`, the error message names the called object.
+    #[inline]
+    fn call_as<T, A>(&self, py: Python, args: A, kwargs: Option<&PyDict>) -> PyResult<T>
+        where T: for<'s> FromPyObject<'s>, A: ToPyObject<ObjectType=PyTuple>
+    {
+        extract_as(py, &self.call(py, args, kwargs)?, || {
+            // Python 2 doesn't have `__qualname__`.
+            let name = self.getattr(py, "__qualname__")
+                .or_else(|_| self.getattr(py, "__name__"))
+                .and_then(|name| name.extract::<String>(py))
+                .unwrap_or_else(|_| self.as_object().get_type(py).name(py).into_owned());
+            Some(format!("return value of '{}'", name))
+        })
+    }
+
     /// Retrieves the hash code of the object.
     /// This is equivalent to the Python expression: 'hash(self)'
     #[inline]
@@ -266,6 +371,20 @@ pub trait ObjectProtocol : PythonObject {
         })
     }
 
+    /// Retrieves an item and extracts it as a `T`.
+    ///
+    /// If the extraction fails with `TypeError`, the error message names the key.
+    #[inline]
+    fn get_item_as<T, K>(&self, py: Python, key: K) -> PyResult<T>
+        where T: for<'s> FromPyObject<'s>, K: ToPyObject
+    {
+        let key = key.into_py_object(py).into_object();
+        extract_as(py, &self.get_item(py, &key)?, || {
+            let key = key.repr(py).map(|r| r.to_string_lossy(py).into_owned()).unwrap_or_default();
+            Some(format!("item {} of '{}' object", key, self.as_object().get_type(py).name(py)))
+        })
+    }
+
     /// Sets an item value.
     /// This is equivalent to the Python expression 'self[key] = value'.
     #[inline]
@@ -297,36 +416,115 @@ pub trait ObjectProtocol : PythonObject {
         }?;
         Ok(::objects::PyIterator::from_object(py, obj)?)
     }
+
+    /// Enters the runtime context of a context manager, like the Python `with` statement.
+    ///
+    /// Calls `self.__enter__()`; the returned guard calls `self.__exit__()` when it is
+    /// dropped, unless `ContextGuard::exit()` or `ContextGuard::exit_with_error()`
+    /// was called before.
+    #[inline]
+    fn enter_context(&self, py: Python) -> PyResult<::ContextGuard> {
+        ::context::ContextGuard::enter(py, self.as_object())
+    }
+}
+
+/// Extracts `obj` as a `T`. A `TypeError` raised by the extraction is rewritten with a message
+/// naming the type of `obj`, prefixed with `context()` if it returns a value,
+/// e.g. `context: unexpected 'str' object: ...`.
+/// With the `nightly` feature, the message also names `T`: `context: expected i32, got 'str' object: ...`.
+///
+/// Other errors are returned unchanged: the constructors of some exception types
+/// (e.g. `UnicodeEncodeError`) don't accept a single message.
+pub fn extract_as<T, F>(py: Python, obj: &PyObject, context: F) -> PyResult<T>
+    where T: for<'s> FromPyObject<'s>, F: FnOnce() -> Option<String>
+{
+    obj.extract::<T>(py).map_err(|mut err| {
+        if !err.matches(py, py.get_type::<::exc::TypeError>()) {
+            return err;
+        }
+        let message = err.instance(py).str(py)
+            .map(|s| s.to_string_lossy(py).into_owned()).unwrap_or_default();
+        let prefix = context().map(|context| context + ": ").unwrap_or_default();
+        #[cfg(feature="nightly")]
+        let expected = Some(unsafe { ::std::intrinsics::type_name::<T>() });
+        #[cfg(not(feature="nightly"))]
+        let expected: Option<&str> = None;
+        let message = match expected {
+            Some(name) => format!("{}expected {}, got '{}' object: {}",
+                prefix, name, obj.get_type(py).name(py), message),
+            None => format!("{}unexpected '{}' object: {}", prefix, obj.get_type(py).name(py), message),
+        };
+        PyErr::new::<::exc::TypeError, _>(py, message)
+    })
 }
 
 impl ObjectProtocol for PyObject {}
 
+/// Iterator over the attributes of an object, created by `ObjectProtocol::attributes()`.
+pub struct Attributes<'p> {
+    py: Python<'p>,
+    obj: PyObject,
+    names: PyList,
+    index: usize,
+}
+
+impl <'p> Iterator for Attributes<'p> {
+    type Item = PyResult<(String, PyObject)>;
+
+    fn next(&mut self) -> Option<PyResult<(String, PyObject)>> {
+        let py = self.py;
+        if self.index >= self.names.len(py) {
+            return None;
+        }
+        let name = self.names.get_item(py, self.index);
+        self.index += 1;
+        Some(name.extract::<String>(py).and_then(|name| {
+            let value = self.obj.getattr(py, &name)?;
+            Ok((name, value))
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.names.len(self.py) - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+/// Writes `s`, or a placeholder naming the type of `obj` if `repr()` or `str()` failed;
+/// returning `fmt::Error` would make `format!()` panic.
+fn write_or_placeholder(py: Python, f: &mut fmt::Formatter, obj: &PyObject, s: PyResult<PyString>, function: &str)
+    -> Result<(), fmt::Error>
+{
+    match s {
+        Ok(s) => f.write_str(&s.to_string_lossy(py)),
+        Err(_) => write!(f, "<{} object ({}() failed)>", obj.get_type(py).name(py), function),
+    }
+}
+
+/// Formats the object using Python's `repr()`. Acquires the GIL.
 impl fmt::Debug for PyObject {
     fn fmt(&self, f : &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        // TODO: we shouldn't use fmt::Error when repr() fails
         let gil_guard = Python::acquire_gil();
         let py = gil_guard.python();
-        let repr_obj = self.repr(py).map_err(|_| fmt::Error)?;
-        f.write_str(&repr_obj.to_string_lossy(py))
+        write_or_placeholder(py, f, self, self.repr(py), "repr")
     }
 }
 
+/// Formats the object using Python's `str()`. Acquires the GIL.
 impl fmt::Display for PyObject {
     fn fmt(&self, f : &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        // TODO: we shouldn't use fmt::Error when str() fails
         let gil_guard = Python::acquire_gil();
         let py = gil_guard.python();
-        let str_obj = self.str(py).map_err(|_| fmt::Error)?;
-        f.write_str(&str_obj.to_string_lossy(py))
+        write_or_placeholder(py, f, self, self.str(py), "str")
     }
 }
 
 #[cfg(test)]
 mod test {
     use std;
-    use python::{Python, PythonObject};
+    use python::{Python, PythonObject, PyClone};
     use conversion::ToPyObject;
-    use objects::{PyList, PyTuple};
+    use objects::{PyDict, PyList, PyTuple};
     use super::ObjectProtocol;
 
     #[test]
@@ -345,6 +543,106 @@ mod test {
         assert_eq!(format!("{}", v), "Hello\n");
     }
 
+    #[test]
+    fn test_wrapper_formatting() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let list = PyList::new(py, &["a".to_py_object(py).into_object()]);
+        assert_eq!(format!("{:?} {}", list, list), "['a'] ['a']");
+        let d = PyDict::new(py);
+        py.run("class Broken(object):\n    def __repr__(self): raise ValueError()", None, Some(&d)).unwrap();
+        let broken = py.eval("Broken()", None, Some(&d)).unwrap();
+        assert_eq!(format!("{:?}", broken), "<Broken object (repr() failed)>");
+    }
+
+    #[test]
+    fn test_attributes() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let d = PyDict::new(py);
+        py.run("class C(object):\n\
+                \x20   __slots__ = ('x', 'y', 'z')\n\
+                \x20   @property\n\
+                \x20   def broken(self): raise ValueError()\n\
+                c = C()\n\
+                c.x = 1\n\
+                c.z = 'z'",
+            None, Some(&d)).unwrap();
+        let c = d.get_item(py, "c").unwrap();
+        assert!(c.hasattr(py, "x").unwrap());
+        assert!(!c.hasattr(py, "y").unwrap());
+        assert!(!c.hasattr(py, "broken").unwrap());
+        let names: Vec<String> = c.dir(py).unwrap().into_object().extract(py).unwrap();
+        assert!(names.contains(&"__slots__".to_owned()));
+        assert!(names.windows(2).all(|w| w[0] <= w[1]));
+
+        let mut values = Vec::new();
+        let mut errors = Vec::new();
+        for attr in c.attributes(py).unwrap() {
+            match attr {
+                Ok((ref name, _)) if name.starts_with("__") => {}
+                Ok((name, value)) => values.push((name, value.to_string())),
+                Err(e) => errors.push(e),
+            }
+        }
+        assert_eq!(values, vec![("x".to_owned(), "1".to_owned()), ("z".to_owned(), "z".to_owned())]);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].matches(py, py.get_type::<::exc::ValueError>()));
+        assert!(errors[1].matches(py, py.get_type::<::exc::AttributeError>()));
+    }
+
+    #[test]
+    fn test_hash_len_is_true() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let s = "abc".to_py_object(py).into_object();
+        assert_eq!(s.hash(py).unwrap(), py.eval("hash('abc')", None, None).unwrap().extract(py).unwrap());
+        assert_eq!(s.len(py).unwrap(), 3);
+        assert!(s.is_true(py).unwrap());
+        let empty = PyList::new(py, &[]).into_object();
+        assert_eq!(empty.len(py).unwrap(), 0);
+        assert!(!empty.is_true(py).unwrap());
+        assert!(empty.hash(py).is_err());
+        assert!(1i32.to_py_object(py).into_object().len(py).is_err());
+    }
+
+    #[test]
+    fn test_compare_bool() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let one = 1i32.to_py_object(py).into_object();
+        assert!(one.lt(py, 2).unwrap());
+        assert!(one.le(py, 1).unwrap());
+        assert!(!one.gt(py, 1).unwrap());
+        assert!(one.ge(py, 0.5).unwrap());
+        assert!(one.rich_eq(py, 1.0).unwrap());
+        assert!(one.rich_ne(py, "1").unwrap());
+        if cfg!(feature="python3-sys") {
+            // Python 2 orders objects of different types.
+            assert!(one.lt(py, "1").is_err());
+        }
+
+        let list = PyList::new(py, &[one.clone_ref(py)]).into_object();
+        assert!(list.rich_eq(py, vec![1]).unwrap());
+        assert!(list.lt(py, vec![2]).unwrap());
+        assert!(!list.rich_ne(py, &list).unwrap());
+        let mut v = vec![3i32, 1, 2].into_iter().map(|i| i.to_py_object(py).into_object()).collect::<Vec<_>>();
+        v.sort_by(|a, b| if a.lt(py, b).unwrap() { std::cmp::Ordering::Less } else { std::cmp::Ordering::Greater });
+        assert_eq!(v.iter().map(|o| o.extract::<i32>(py).unwrap()).collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        // `PartialEq` still compares identities, on PyObject and on PyType.
+        let other = 1.0f64.to_py_object(py).into_object();
+        assert!(one.eq(&one));
+        assert!(!one.eq(&other));
+        assert!(one.rich_eq(py, &other).unwrap());
+        let int_type = one.get_type(py);
+        let list_type = list.get_type(py);
+        assert!(int_type.eq(&int_type));
+        assert!(!int_type.eq(&list_type));
+        assert!(int_type.as_object().rich_eq(py, &int_type).unwrap());
+        assert!(int_type.as_object().rich_ne(py, &list_type).unwrap());
+    }
+
     #[test]
     fn test_compare() {
         use std::cmp::Ordering;
@@ -355,5 +653,48 @@ mod test {
         assert_eq!(one.compare(py, 2).unwrap(), Ordering::Less);
         assert_eq!(one.compare(py, 0).unwrap(), Ordering::Greater);
     }
-}
 
+    #[test]
+    fn test_typed_helpers() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let d = PyDict::new(py);
+        py.run("class C(object):\n\
+                \x20   x = 1\n\
+                \x20   name = 'c'\n\
+                \x20   def f(self): return 'text'\n\
+                c = C()",
+            None, Some(&d)).unwrap();
+        let c = d.get_item(py, "c").unwrap();
+        assert_eq!(c.getattr_as::<u32>(py, "x").unwrap(), 1);
+        assert_eq!(c.getattr(py, "f").unwrap().call_as::<String, _>(py, ::NoArgs, None).unwrap(), "text");
+        assert!(d.as_object().get_item_as::<u32, _>(py, "c").is_err());
+
+        let mut err = c.getattr_as::<u32>(py, "name").unwrap_err();
+        let message = err.instance(py).str(py).unwrap().to_string_lossy(py).into_owned();
+        assert!(message.starts_with("attribute 'name' of 'C' object: "), "{}", message);
+        let mut err = c.getattr(py, "f").unwrap().call_as::<i32, _>(py, ::NoArgs, None).unwrap_err();
+        let message = err.instance(py).str(py).unwrap().to_string_lossy(py).into_owned();
+        let expected = if cfg!(feature="python27-sys") { "return value of 'f': " } else { "return value of 'C.f': " };
+        assert!(message.starts_with(expected), "{}", message);
+        let list = PyList::new(py, &[1i32.to_py_object(py).into_object()]).into_object();
+        let mut err = list.get_item_as::<String, _>(py, 0).unwrap_err();
+        let message = err.instance(py).str(py).unwrap().to_string_lossy(py).into_owned();
+        assert!(message.starts_with("item 0 of 'list' object: "), "{}", message);
+        assert!(message.contains("'int' object: "), "{}", message);
+    }
+
+    #[test]
+    fn test_typed_helpers_other_errors() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let list = PyList::new(py, &[1000i32.to_py_object(py).into_object()]).into_object();
+        // Only TypeError is rewritten; other exceptions are returned unchanged.
+        let mut err = list.get_item_as::<u8, _>(py, 0).unwrap_err();
+        assert!(err.matches(py, py.get_type::<::exc::OverflowError>()));
+        let message = err.instance(py).str(py).unwrap().to_string_lossy(py).into_owned();
+        assert!(!message.contains("'int' object: "), "{}", message);
+        let err = list.get_item(py, 0).unwrap().extract_checked::<u8>(py).unwrap_err();
+        assert!(err.matches(py, py.get_type::<::exc::OverflowError>()));
+    }
+}
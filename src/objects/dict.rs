@@ -21,7 +21,7 @@ use python::{Python, PythonObject};
 use conversion::ToPyObject;
 use objects::{PyObject, PyList};
 use err::{self, PyResult, PyErr};
-use std::{mem, collections, hash, cmp};
+use std::{mem, ptr, collections, hash, cmp};
 
 /// Represents a Python `dict`.
 pub struct PyDict(PyObject);
@@ -124,6 +124,51 @@ impl PyDict {
         }
         vec
     }
+
+    /// Iterates over the (key, value) pairs of the dictionary.
+    ///
+    /// Like the iteration of dictionaries in Python, the iterator yields a `RuntimeError`
+    /// and then stops if the size of the dictionary changes during the iteration
+    /// (e.g. because Python code called by the loop added an item).
+    /// The values can be replaced during the iteration.
+    #[inline]
+    pub fn iter_checked<'a, 'p>(&'a self, py: Python<'p>) -> PyDictIterator<'a, 'p> {
+        PyDictIterator { py, dict: self, pos: 0, len: Some(self.len(py)) }
+    }
+}
+
+/// Used by `PyDict::iter_checked()`.
+pub struct PyDictIterator<'a, 'p> {
+    py: Python<'p>,
+    dict: &'a PyDict,
+    pos: ffi::Py_ssize_t,
+    /// The initial length of the dictionary, or `None` after an error.
+    len: Option<usize>
+}
+
+impl <'a, 'p> Iterator for PyDictIterator<'a, 'p> {
+    type Item = PyResult<(PyObject, PyObject)>;
+
+    fn next(&mut self) -> Option<PyResult<(PyObject, PyObject)>> {
+        let len = self.len?;
+        if self.dict.len(self.py) != len {
+            self.len = None;
+            return Some(Err(PyErr::new::<::exc::RuntimeError, _>(self.py,
+                "dictionary changed size during iteration")));
+        }
+        // PyDict_Next() checks `pos` against the current entries of the dictionary:
+        // it doesn't read freed memory if the dictionary was resized.
+        let mut key: *mut ffi::PyObject = ptr::null_mut();
+        let mut value: *mut ffi::PyObject = ptr::null_mut();
+        unsafe {
+            if ffi::PyDict_Next(self.dict.0.as_ptr(), &mut self.pos, &mut key, &mut value) != 0 {
+                Some(Ok((PyObject::from_borrowed_ptr(self.py, key),
+                         PyObject::from_borrowed_ptr(self.py, value))))
+            } else {
+                None
+            }
+        }
+    }
 }
 
 impl <K, V, H> ToPyObject for collections::HashMap<K, V, H>
@@ -161,7 +206,7 @@ impl <K, V> ToPyObject for collections::BTreeMap<K, V>
 mod test {
     use python::{Python, PythonObject};
     use conversion::ToPyObject;
-    use objects::{PyDict, PyTuple};
+    use objects::{PyDict, PyTuple, exc};
     use std::collections::HashMap;
 
     #[test]
@@ -289,4 +334,29 @@ mod test {
         assert_eq!(32 + 42 + 123, value_sum);
     }
 
+    #[test]
+    fn test_iter_checked() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let mut v = HashMap::new();
+        v.insert(7, 32);
+        v.insert(8, 42);
+        let dict = v.to_py_object(py);
+        let mut key_sum = 0;
+        for item in dict.iter_checked(py) {
+            let (key, value) = item.unwrap();
+            key_sum += key.extract::<i32>(py).unwrap();
+            // Replacing values is allowed.
+            dict.set_item(py, key, value.extract::<i32>(py).unwrap() + 1).unwrap();
+        }
+        assert_eq!(7 + 8, key_sum);
+        assert_eq!(43, dict.get_item(py, 8).unwrap().extract::<i32>(py).unwrap());
+
+        let mut iter = dict.iter_checked(py);
+        assert!(iter.next().unwrap().is_ok());
+        dict.set_item(py, 10, 0).unwrap();
+        let err = iter.next().unwrap().unwrap_err();
+        assert!(err.matches(py, py.get_type::<exc::RuntimeError>()));
+        assert!(iter.next().is_none());
+    }
 }
@@ -80,6 +80,16 @@ impl PyList {
     pub fn iter<'a, 'p>(&'a self, py: Python<'p>) -> PyListIterator<'a, 'p> {
         PyListIterator { py: py, list: self, index: 0 }
     }
+
+    /// Iterates over the items of the list, failing if the list changes size during the iteration.
+    ///
+    /// When the size of the list changes (e.g. because Python code called
+    /// by the loop appended an item), the iterator yields a `RuntimeError`
+    /// and then stops, instead of skipping or repeating items.
+    #[inline]
+    pub fn iter_checked<'a, 'p>(&'a self, py: Python<'p>) -> PyListCheckedIterator<'a, 'p> {
+        PyListCheckedIterator { py, list: self, index: 0, len: Some(self.len(py)) }
+    }
 }
 
 /// Used by `PyList::iter()`.
@@ -107,6 +117,43 @@ impl <'a, 'p> Iterator for PyListIterator<'a, 'p> {
     // might change during the iteration.
 }
 
+/// Used by `PyList::iter_checked()`.
+pub struct PyListCheckedIterator<'a, 'p> {
+    py: Python<'p>,
+    list: &'a PyList,
+    index: usize,
+    /// The initial length of the list, or `None` after an error.
+    len: Option<usize>
+}
+
+impl <'a, 'p> Iterator for PyListCheckedIterator<'a, 'p> {
+    type Item = PyResult<PyObject>;
+
+    fn next(&mut self) -> Option<PyResult<PyObject>> {
+        let len = self.len?;
+        if self.list.len(self.py) != len {
+            self.len = None;
+            return Some(Err(PyErr::new::<::exc::RuntimeError, _>(self.py,
+                "list changed size during iteration")));
+        }
+        if self.index < len {
+            let item = self.list.get_item(self.py, self.index);
+            self.index += 1;
+            Some(Ok(item))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.len {
+            // One more item for the error.
+            Some(len) => (0, Some(len - self.index + 1)),
+            None => (0, Some(0))
+        }
+    }
+}
+
 impl <T> ToPyObject for [T] where T: ToPyObject {
     type ObjectType = PyList;
 
@@ -147,7 +194,7 @@ impl <T> ToPyObject for Vec<T> where T: ToPyObject {
 mod test {
     use python::{Python, PythonObject};
     use conversion::ToPyObject;
-    use objects::PyList;
+    use objects::{PyList, exc};
 
     #[test]
     fn test_len() {
@@ -211,6 +258,23 @@ mod test {
         assert_eq!(idx, v.len());
     }
 
+    #[test]
+    fn test_iter_checked() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let v = vec![2, 3, 5, 7];
+        let list = v.to_py_object(py);
+        let items: Vec<i32> = list.iter_checked(py).map(|el| el.unwrap().extract(py).unwrap()).collect();
+        assert_eq!(v, items);
+
+        let mut iter = list.iter_checked(py);
+        assert!(iter.next().unwrap().is_ok());
+        list.insert_item(py, 0, 1i32.to_py_object(py).into_object());
+        let err = iter.next().unwrap().unwrap_err();
+        assert!(err.matches(py, py.get_type::<exc::RuntimeError>()));
+        assert!(iter.next().is_none());
+    }
+
     #[test]
     fn test_extract() {
         let gil = Python::acquire_gil();
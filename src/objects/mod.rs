@@ -29,15 +29,17 @@ pub use self::string::PyString as PyUnicode;
 pub use self::iterator::PyIterator;
 pub use self::boolobject::PyBool;
 pub use self::tuple::{PyTuple, NoArgs};
-pub use self::dict::PyDict;
-pub use self::list::PyList;
+pub use self::dict::{PyDict, PyDictIterator};
+pub use self::list::{PyList, PyListCheckedIterator};
 #[cfg(feature="python27-sys")]
 pub use self::num::PyInt;
 #[cfg(feature="python3-sys")]
 pub use self::num::PyLong as PyInt;
 pub use self::num::{PyLong, PyFloat};
-pub use self::sequence::PySequence;
+pub use self::sequence::{PySequence, SliceBounds, SliceRange, Step};
 pub use self::capsule::PyCapsule;
+pub use self::typed::{PyTypedList, PyTypedListIterator, PyTypedListIntoIter,
+                      PyTypedDict, PyTypedDictIterator, PyTypedDictIntoIter};
 
 #[macro_export]
 macro_rules! pyobject_newtype(
@@ -70,6 +72,20 @@ macro_rules! pyobject_newtype(
                 ::std::mem::transmute(obj)
             }
         }
+
+        /// Formats the object using Python's `repr()`, like `PyObject`.
+        impl ::std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+                ::std::fmt::Debug::fmt(&self.0, f)
+            }
+        }
+
+        /// Formats the object using Python's `str()`, like `PyObject`.
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+                ::std::fmt::Display::fmt(&self.0, f)
+            }
+        }
     );
     ($name: ident, $checkfunction: ident) => (
         pyobject_newtype!($name);
@@ -134,6 +150,7 @@ mod list;
 mod num;
 mod sequence;
 mod capsule;
+mod typed;
 pub mod exc;
 
 #[cfg(feature="python27-sys")]
@@ -133,6 +133,90 @@ impl PyModule {
     {
         T::add_to_module(py, self)
     }
+
+    /// Adds a function created with `py_fn!()` to the module.
+    ///
+    /// This is a convenience function that sets `function.__module__` to this module's name,
+    /// and adds the function to this module under `name`.
+    /// Note that `name` does not change the `__name__` of the function, which is
+    /// the name of the wrapped Rust function.
+    ///
+    /// ```
+    /// #[macro_use] extern crate cpython;
+    /// use cpython::{Python, PyModule, PyResult, ObjectProtocol};
+    ///
+    /// fn double(_py: Python, x: i64) -> PyResult<i64> {
+    ///     Ok(x * 2)
+    /// }
+    ///
+    /// fn main() {
+    ///     let gil = Python::acquire_gil();
+    ///     let py = gil.python();
+    ///     let m = PyModule::new(py, "example").unwrap();
+    ///     m.add_function(py, "double", py_fn!(py, double(x: i64))).unwrap();
+    ///     let f = m.get(py, "double").unwrap();
+    ///     assert_eq!(f.getattr(py, "__module__").unwrap().extract::<String>(py).unwrap(), "example");
+    ///     assert_eq!(m.call(py, "double", (21,), None).unwrap().extract::<i64>(py).unwrap(), 42);
+    /// }
+    /// ```
+    pub fn add_function(&self, py: Python, name: &str, function: PyObject) -> PyResult<()> {
+        function.setattr(py, "__module__", self.name(py)?)?;
+        self.add(py, name, function)
+    }
+
+    /// Creates a submodule of this module, e.g. `mypkg.core` for the module `mypkg`
+    /// and the name `core`.
+    ///
+    /// The submodule is added to this module, and registered in `sys.modules` under its
+    /// full name, so that `import mypkg.core` and `from mypkg.core import ...` work
+    /// once this module is imported.
+    /// This can be used to declare several modules from a single extension module:
+    ///
+    /// ```
+    /// #[macro_use] extern crate cpython;
+    /// use cpython::{Python, PyResult};
+    ///
+    /// py_module_initializer!(mypkg, initmypkg, PyInit_mypkg, |py, m| {
+    ///     let core = m.new_submodule(py, "core")?;
+    ///     core.add(py, "answer", 42)?;
+    ///     Ok(())
+    /// });
+    /// # fn main() {}
+    /// ```
+    pub fn new_submodule(&self, py: Python, name: &str) -> PyResult<PyModule> {
+        let full_name = format!("{}.{}", self.name(py)?, name);
+        let submodule = PyModule::new(py, &full_name)?;
+        py.import("sys")?.get(py, "modules")?.set_item(py, &full_name, &submodule)?;
+        self.add(py, name, &submodule)?;
+        Ok(submodule)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use python::{Python, PythonObject};
+    use objects::{PyDict, PyModule};
+    use objectprotocol::ObjectProtocol;
+
+    #[test]
+    fn test_new_submodule() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let m = PyModule::new(py, "rust_cpython_test_pkg").unwrap();
+        let core = m.new_submodule(py, "core").unwrap();
+        core.add(py, "answer", 42).unwrap();
+        let util = core.new_submodule(py, "util").unwrap();
+        assert_eq!(util.name(py).unwrap(), "rust_cpython_test_pkg.core.util");
+        py.import("sys").unwrap().get(py, "modules").unwrap()
+            .set_item(py, "rust_cpython_test_pkg", &m).unwrap();
+
+        let d = PyDict::new(py);
+        py.run("from rust_cpython_test_pkg.core import answer\n\
+                import rust_cpython_test_pkg.core.util as util",
+            None, Some(&d)).unwrap();
+        assert_eq!(d.get_item(py, "answer").unwrap().extract::<i32>(py).unwrap(), 42);
+        assert!(d.get_item(py, "util").unwrap() == *util.as_object());
+    }
 }
 
 
@@ -108,6 +108,7 @@ impl PyObject {
     /// Undefined behavior if the pointer is NULL or invalid.
     #[inline]
     pub unsafe fn from_owned_ptr(_py: Python, ptr: *mut ffi::PyObject) -> PyObject {
+        ::gilcheck::assert_gil_held();
         debug_assert!(!ptr.is_null() && ffi::Py_REFCNT(ptr) > 0);
         PyObject { ptr: ptr::NonNull::new_unchecked(ptr) }
     }
@@ -117,6 +118,7 @@ impl PyObject {
     /// Undefined behavior if the pointer is NULL or invalid.
     #[inline]
     pub unsafe fn from_borrowed_ptr(_py : Python, ptr : *mut ffi::PyObject) -> PyObject {
+        ::gilcheck::assert_gil_held();
         debug_assert!(!ptr.is_null() && ffi::Py_REFCNT(ptr) > 0);
         ffi::Py_INCREF(ptr);
         PyObject { ptr: ptr::NonNull::new_unchecked(ptr) }
@@ -237,6 +239,28 @@ impl PyObject {
     {
         ::conversion::FromPyObject::extract(py, self)
     }
+
+    /// Extracts some type from the Python object, like `extract()`.
+    ///
+    /// If the extraction fails with `TypeError`, the message of the error is prefixed with
+    /// the actual Python type of the object, e.g. `unexpected 'str' object: ...`.
+    /// With the `nightly` feature, it also names the expected Rust type:
+    /// `expected i32, got 'str' object: ...`. Other errors are returned unchanged.
+    pub fn extract_checked<T>(&self, py: Python) -> PyResult<T>
+        where T: for<'s> ::conversion::FromPyObject<'s>
+    {
+        ::objectprotocol::extract_as::<T, _>(py, self, || None)
+    }
+
+    /// Returns true if the object is an instance of `T` (or of a subclass of `T`).
+    /// This is equivalent to the Python expression `isinstance(self, T)`,
+    /// without looking up `__instancecheck__`.
+    #[inline]
+    pub fn is_instance_of<T>(&self, py: Python) -> bool
+        where T: PythonObjectWithTypeObject
+    {
+        T::type_object(py).is_instance(py, self)
+    }
 }
 
 /// PyObject implements the `==` operator using reference equality:
@@ -260,3 +284,32 @@ fn test_sizeof() {
     assert_eq!(mem::size_of::<PyType>(), mem::size_of::<*mut ffi::PyTypeObject>());
 }
 
+#[test]
+fn test_type_checks() {
+    use objects::{PyDict, PyInt, PyList};
+    use exc;
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let d = PyDict::new(py);
+    py.run("class D(dict): pass", None, Some(&d)).unwrap();
+    let obj = py.eval("D()", None, Some(&d)).unwrap();
+    assert!(obj.is_instance_of::<PyDict>(py));
+    assert!(!obj.is_instance_of::<PyList>(py));
+    assert!(obj.get_type(py).is_subclass_of::<PyDict>(py));
+    assert!(!py.get_type::<PyInt>().is_subclass_of::<PyDict>(py));
+
+    let obj = py.eval("'abc'", None, None).unwrap();
+    let mut err = obj.extract_checked::<i32>(py).unwrap_err();
+    assert!(err.matches(py, py.get_type::<exc::TypeError>()));
+    let message = err.instance(py).to_string();
+    if cfg!(feature="nightly") {
+        assert!(message.starts_with("expected i32, got 'str' object: "), "{}", message);
+    } else {
+        assert!(message.starts_with("unexpected 'str' object: "), "{}", message);
+    }
+    let obj = py.eval("2 ** 40", None, None).unwrap();
+    let err = obj.extract_checked::<i32>(py).unwrap_err();
+    assert!(err.matches(py, py.get_type::<exc::OverflowError>()));
+    assert_eq!(obj.extract_checked::<i64>(py).unwrap(), 1 << 40);
+}
+
@@ -16,7 +16,7 @@
 // OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use std::mem;
+use std::{mem, ops, ptr};
 use ffi;
 use python::{Python, PythonObject, ToPythonPointer, PyClone, PyDrop};
 use conversion::{FromPyObject, ToPyObject};
@@ -29,6 +29,108 @@ use buffer;
 /// Represents a reference to a python object supporting the sequence protocol.
 pub struct PySequence(PyObject);
 
+/// A Rust range type (`a..b`, `a..`, `..b` or `..`), usable as the bounds of a Python slice.
+/// With the `nightly` feature, the inclusive ranges `a..=b` and `..=b` are supported too.
+///
+/// As in Python, negative bounds count from the end of the sequence.
+pub trait SliceBounds {
+    /// Returns the `start` and `stop` of the Python slice with the given step;
+    /// `None` leaves the bound open.
+    fn slice_bounds(&self, step: isize) -> (Option<isize>, Option<isize>);
+}
+
+/// A range that can be used to slice a Python sequence: either a Rust range type,
+/// or a range with a `Step`.
+pub trait SliceRange {
+    /// Returns the `start`, `stop` and `step` of the Python slice.
+    fn slice_indices(&self) -> (Option<isize>, Option<isize>, isize);
+
+    /// Creates the Python `slice` object for the range.
+    fn to_slice(&self, py: Python) -> PyResult<PyObject> {
+        fn index(py: Python, i: Option<isize>) -> Option<PyObject> {
+            i.map(|i| i.to_py_object(py).into_object())
+        }
+        let (start, stop, step) = self.slice_indices();
+        let (start, stop) = (index(py, start), index(py, stop));
+        let step = step.to_py_object(py).into_object();
+        let ptr = |o: &Option<PyObject>| o.as_ref().map_or(ptr::null_mut(), |o| o.as_ptr());
+        unsafe {
+            result_from_owned_ptr(py, ffi::PySlice_New(ptr(&start), ptr(&stop), step.as_ptr()))
+        }
+    }
+}
+
+/// A range with a step, e.g. `Step(.., -1)` for the Python slice `[::-1]`.
+///
+/// With a negative step, the range goes from its start down to its end:
+/// `Step(5..1, -2)` is the Python slice `[5:1:-2]`, and `Step(5.., -1)` goes down to
+/// the first item of the sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step<R>(pub R, pub isize);
+
+impl <R: SliceBounds> SliceRange for R {
+    fn slice_indices(&self) -> (Option<isize>, Option<isize>, isize) {
+        let (start, stop) = self.slice_bounds(1);
+        (start, stop, 1)
+    }
+}
+
+impl <R: SliceBounds> SliceRange for Step<R> {
+    fn slice_indices(&self) -> (Option<isize>, Option<isize>, isize) {
+        let (start, stop) = self.0.slice_bounds(self.1);
+        (start, stop, self.1)
+    }
+}
+
+/// Converts the inclusive end of a range to the exclusive `stop` of a slice.
+/// The bounds that would wrap around the end (or the start) of the sequence are left open.
+#[cfg(feature="nightly")]
+fn inclusive_stop(end: isize, step: isize) -> Option<isize> {
+    if step < 0 {
+        if end == 0 { None } else { end.checked_sub(1) }
+    } else {
+        if end == -1 { None } else { end.checked_add(1) }
+    }
+}
+
+impl SliceBounds for ops::Range<isize> {
+    fn slice_bounds(&self, _step: isize) -> (Option<isize>, Option<isize>) {
+        (Some(self.start), Some(self.end))
+    }
+}
+
+impl SliceBounds for ops::RangeFrom<isize> {
+    fn slice_bounds(&self, _step: isize) -> (Option<isize>, Option<isize>) {
+        (Some(self.start), None)
+    }
+}
+
+impl SliceBounds for ops::RangeTo<isize> {
+    fn slice_bounds(&self, _step: isize) -> (Option<isize>, Option<isize>) {
+        (None, Some(self.end))
+    }
+}
+
+impl SliceBounds for ops::RangeFull {
+    fn slice_bounds(&self, _step: isize) -> (Option<isize>, Option<isize>) {
+        (None, None)
+    }
+}
+
+#[cfg(feature="nightly")]
+impl SliceBounds for ops::RangeInclusive<isize> {
+    fn slice_bounds(&self, step: isize) -> (Option<isize>, Option<isize>) {
+        (Some(*self.start()), inclusive_stop(*self.end(), step))
+    }
+}
+
+#[cfg(feature="nightly")]
+impl SliceBounds for ops::RangeToInclusive<isize> {
+    fn slice_bounds(&self, step: isize) -> (Option<isize>, Option<isize>) {
+        (None, inclusive_stop(self.end, step))
+    }
+}
+
 pyobject_newtype!(PySequence, PySequence_Check);
 
 impl PySequence {
@@ -99,6 +201,17 @@ impl PySequence {
         }
     }
 
+    /// Return the slice of sequence object o for the given range, e.g. `1..10`, `..6`
+    /// or `Step(.., -1)`.
+    /// This is the equivalent of the Python expression `o[1:10]`, `o[:6]` or `o[::-1]`.
+    #[inline]
+    pub fn slice<R: SliceRange>(&self, py: Python, range: R) -> PyResult<PyObject> {
+        let slice = range.to_slice(py)?;
+        unsafe {
+            result_from_owned_ptr(py, ffi::PyObject_GetItem(self.as_ptr(), slice.as_ptr()))
+        }
+    }
+
     /// Assign object v to the ith element of o.
     /// Equivalent to Python statement `o[i] = v`
     #[inline]
@@ -139,6 +252,28 @@ impl PySequence {
         }
     }
 
+    /// Assign the sequence object v to the slice in sequence object o for the given range.
+    /// This is the equivalent of the Python statement `o[i1:i2] = v` for `range = i1..i2`
+    #[inline]
+    pub fn set_range<R: SliceRange>(&self, py: Python, range: R, v: &PyObject) -> PyResult<()> {
+        let slice = range.to_slice(py)?;
+        unsafe {
+            err::error_on_minusone(py,
+                ffi::PyObject_SetItem(self.as_ptr(), slice.as_ptr(), v.as_ptr()))
+        }
+    }
+
+    /// Delete the slice in sequence object o for the given range.
+    /// equivalent of the Python statement `del o[i1:i2]` for `range = i1..i2`
+    #[inline]
+    pub fn del_range<R: SliceRange>(&self, py: Python, range: R) -> PyResult<()> {
+        let slice = range.to_slice(py)?;
+        unsafe {
+            err::error_on_minusone(py,
+                ffi::PyObject_DelItem(self.as_ptr(), slice.as_ptr()))
+        }
+    }
+
     /// Return the number of occurrences of value in o, that is, return the number of keys for
     /// which `o[key] == value`
     #[inline]
@@ -266,6 +401,7 @@ mod test {
     use python::{Python, PythonObject};
     use conversion::ToPyObject;
     use objects::{PySequence, PyList, PyTuple, PyIterator};
+    use super::{SliceRange, Step};
 
     #[test]
     fn test_numbers_are_not_sequences() {
@@ -332,9 +468,104 @@ mod test {
         assert!(seq.get_item(py, 10).is_err());
     }
 
-    // fn test_get_slice() {}
-    // fn test_set_slice() {}
-    // fn test_del_slice() {}
+    #[test]
+    fn test_get_slice() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let v : Vec<i32> = vec![1, 1, 2, 3, 5, 8];
+        let seq = v.to_py_object(py).into_object().cast_into::<PySequence>(py).unwrap();
+        assert_eq!(vec![1, 2, 3], seq.get_slice(py, 1, 4).unwrap().extract::<Vec<i32>>(py).unwrap());
+        assert_eq!(vec![3, 5], seq.get_slice(py, -3, -1).unwrap().extract::<Vec<i32>>(py).unwrap());
+        assert_eq!(vec![5, 8], seq.get_slice(py, 4, 10).unwrap().extract::<Vec<i32>>(py).unwrap());
+    }
+
+    #[test]
+    fn test_set_slice() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let v : Vec<i32> = vec![1, 1, 2, 3, 5, 8];
+        let seq = v.to_py_object(py).into_object().cast_into::<PySequence>(py).unwrap();
+        let w = vec![0, 0].to_py_object(py).into_object();
+        seq.set_slice(py, 1, 4, &w).unwrap();
+        assert_eq!(vec![1, 0, 0, 5, 8], seq.as_object().extract::<Vec<i32>>(py).unwrap());
+    }
+
+    #[test]
+    fn test_del_slice() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let v : Vec<i32> = vec![1, 1, 2, 3, 5, 8];
+        let seq = v.to_py_object(py).into_object().cast_into::<PySequence>(py).unwrap();
+        seq.del_slice(py, 1, -1).unwrap();
+        assert_eq!(vec![1, 8], seq.as_object().extract::<Vec<i32>>(py).unwrap());
+    }
+
+    #[test]
+    fn test_slice() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let v : Vec<i32> = vec![1, 1, 2, 3, 5, 8];
+        let seq = v.to_py_object(py).into_object().cast_into::<PySequence>(py).unwrap();
+        fn slice<R: SliceRange>(seq: &PySequence, range: R) -> Vec<i32> {
+            let py = unsafe { Python::assume_gil_acquired() };
+            seq.slice(py, range).unwrap().extract(py).unwrap()
+        }
+        assert_eq!(vec![1, 2, 3], slice(&seq, 1..4));
+        assert_eq!(vec![5, 8], slice(&seq, 4..));
+        assert_eq!(vec![1, 1], slice(&seq, ..2));
+        assert_eq!(v, slice(&seq, ..));
+        assert_eq!(vec![3, 5], slice(&seq, -3..-1));
+        assert_eq!(Vec::<i32>::new(), slice(&seq, 10..20));
+        assert_eq!(vec![8, 5, 3, 2, 1, 1], slice(&seq, Step(.., -1)));
+        assert_eq!(vec![1, 2, 5], slice(&seq, Step(.., 2)));
+        assert_eq!(vec![5, 2], slice(&seq, Step(4..1, -2)));
+        assert_eq!(vec![5, 3, 2, 1, 1], slice(&seq, Step(4.., -1)));
+        assert!(seq.slice(py, Step(.., 0)).is_err());
+    }
+
+    #[test]
+    #[cfg(feature="nightly")]
+    fn test_slice_inclusive() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let v : Vec<i32> = vec![1, 1, 2, 3, 5, 8];
+        let seq = v.to_py_object(py).into_object().cast_into::<PySequence>(py).unwrap();
+        fn slice<R: SliceRange>(seq: &PySequence, range: R) -> Vec<i32> {
+            let py = unsafe { Python::assume_gil_acquired() };
+            seq.slice(py, range).unwrap().extract(py).unwrap()
+        }
+        assert_eq!(vec![1, 2, 3, 5], slice(&seq, 1..=4));
+        assert_eq!(vec![1, 1, 2], slice(&seq, ..=2));
+        assert_eq!(v, slice(&seq, ..=-1));
+        assert_eq!(vec![5, 3, 2, 1, 1], slice(&seq, Step(4..=0, -1)));
+        assert_eq!(vec![8, 5], slice(&seq, Step(..=-2, -1)));
+    }
+
+    #[test]
+    fn test_set_range() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let v : Vec<i32> = vec![1, 1, 2, 3, 5, 8];
+        let seq = v.to_py_object(py).into_object().cast_into::<PySequence>(py).unwrap();
+        let w = vec![0, 0].to_py_object(py).into_object();
+        seq.set_range(py, 1..4, &w).unwrap();
+        assert_eq!(vec![1, 0, 0, 5, 8], seq.as_object().extract::<Vec<i32>>(py).unwrap());
+        seq.set_range(py, Step(.., -3), &w).unwrap();
+        assert_eq!(vec![1, 0, 0, 5, 0], seq.as_object().extract::<Vec<i32>>(py).unwrap());
+        assert!(seq.set_range(py, Step(.., 2), &w).is_err());
+    }
+
+    #[test]
+    fn test_del_range() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let v : Vec<i32> = vec![1, 1, 2, 3, 5, 8];
+        let seq = v.to_py_object(py).into_object().cast_into::<PySequence>(py).unwrap();
+        seq.del_range(py, Step(1.., 2)).unwrap();
+        assert_eq!(vec![1, 2, 5], seq.as_object().extract::<Vec<i32>>(py).unwrap());
+        seq.del_range(py, ..1).unwrap();
+        assert_eq!(vec![2, 5], seq.as_object().extract::<Vec<i32>>(py).unwrap());
+    }
 
     #[test]
     fn test_seq_del_item() {
@@ -21,8 +21,9 @@ use std::{mem, str, char};
 use std::borrow::Cow;
 use libc::c_char;
 use ffi;
-use python::{Python, PythonObject, PyClone, ToPythonPointer, PythonObjectDowncastError};
+use python::{Python, PythonObject, PyClone, PyDrop, ToPythonPointer, PythonObjectDowncastError};
 use super::{exc, PyObject};
+use buffer::PyBuffer;
 use err::{self, PyResult, PyErr};
 use conversion::{FromPyObject, RefFromPyObject, ToPyObject};
 
@@ -437,14 +438,21 @@ impl <'source> FromPyObject<'source> for String {
 
 /// Allows extracting byte arrays from Python objects.
 /// For Python `bytes`, returns a reference to the existing immutable string data.
-/// For other types, converts to an owned `Vec<u8>`.
+/// For other bytes-like objects (`bytearray`, `memoryview`, `array.array`, ...), copies the
+/// raw bytes of the buffer to an owned `Vec<u8>`: these buffers might be mutated while borrowed.
+/// For other types, converts the sequence of integers to an owned `Vec<u8>`.
 impl <'source> FromPyObject<'source> for Cow<'source, [u8]> {
     fn extract(py: Python, obj: &'source PyObject) -> PyResult<Self> {
         if let Ok(bytes) = obj.cast_as::<PyBytes>(py) {
-            Ok(Cow::Borrowed(bytes.data(py)))
-        } else {
-            obj.extract::<Vec<u8>>(py).map(Cow::Owned)
+            return Ok(Cow::Borrowed(bytes.data(py)));
+        }
+        if unsafe { ffi::PyObject_CheckBuffer(obj.as_ptr()) } != 0 {
+            let buf = PyBuffer::get(py, obj)?;
+            let result = buf.to_bytes(py);
+            buf.release_ref(py);
+            return result.map(Cow::Owned);
         }
+        obj.extract::<Vec<u8>>(py).map(Cow::Owned)
     }
 }
 
@@ -468,6 +476,7 @@ impl RefFromPyObject for [u8] {
 
 #[cfg(test)]
 mod test {
+    use std::borrow::Cow;
     use python::{Python, PythonObject};
     use conversion::{ToPyObject, RefFromPyObject};
 
@@ -509,6 +518,34 @@ mod test {
         assert!(called);
     }
 
+    #[test]
+    fn test_extract_bytes_like() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let extract = |code: &str| -> Vec<u8> {
+            let obj = py.eval(code, None, None).unwrap();
+            let bytes = obj.extract::<Cow<[u8]>>(py).unwrap();
+            bytes.into_owned()
+        };
+        assert_eq!(b"Hello", &extract("bytearray(b'Hello')")[..]);
+        assert_eq!(b"ell", &extract("memoryview(b'Hello')[1:4]")[..]);
+        // Python 2 can't slice memoryviews with a step,
+        // and its arrays don't support the new buffer protocol.
+        if cfg!(feature="python3-sys") {
+            // Not contiguous
+            assert_eq!(b"Hlo", &extract("memoryview(b'Hello')[::2]")[..]);
+            assert_eq!(vec![1, 255], extract("__import__('array').array('b', [1, -1])"));
+        }
+        assert_eq!(vec![1, 2], extract("[1, 2]"));
+        assert!(py.eval("[256]", None, None).unwrap().extract::<Cow<[u8]>>(py).is_err());
+
+        let py_bytes = py.eval("b'Hello'", None, None).unwrap();
+        match py_bytes.extract::<Cow<[u8]>>(py).unwrap() {
+            Cow::Borrowed(b) => assert_eq!(b"Hello", b),
+            Cow::Owned(_) => panic!("bytes should be borrowed"),
+        }
+    }
+
     #[test]
     #[cfg(feature="nightly")] // only works with specialization
     fn test_extract_byte_str_to_vec() {
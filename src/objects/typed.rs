@@ -0,0 +1,466 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Typed views of Python lists and dicts.
+//!
+//! `PyTypedList<T>` and `PyTypedDict<K, V>` wrap a `PyList` or a `PyDict` that are
+//! expected to only contain items of the given Rust types. The items are converted
+//! when they are accessed, so the views are cheap to create, and a conversion error
+//! is reported for the item that has the wrong type.
+//!
+//! ```
+//! use cpython::{Python, PyTypedList, PyTypedDict};
+//!
+//! let gil = Python::acquire_gil();
+//! let py = gil.python();
+//! let list: PyTypedList<i32> = py.eval("[1, 2, 3]", None, None).unwrap().extract(py).unwrap();
+//! list.append(py, &4).unwrap();
+//! let sum: i32 = list.iter(py).map(|item| item.unwrap()).sum();
+//! assert_eq!(sum, 10);
+//!
+//! let dict: PyTypedDict<String, f64> = PyTypedDict::new(py);
+//! dict.set_item(py, &"pi".to_owned(), &3.14).unwrap();
+//! assert_eq!(dict.get_item(py, "pi").unwrap(), Some(3.14));
+//! ```
+
+use std::marker::PhantomData;
+use std::vec;
+use ffi;
+use python::{Python, PythonObject, PyClone};
+use conversion::{FromPyObject, ToPyObject};
+use objects::{PyObject, PyList, PyDict};
+use err::{self, PyErr, PyResult};
+use exc;
+
+/// A Python `list` with items of type `T`.
+///
+/// The items are converted from (or to) `T` when they are accessed.
+pub struct PyTypedList<T> {
+    list: PyList,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl <T> PyTypedList<T> {
+    /// Creates a new, empty list.
+    pub fn new(py: Python) -> PyTypedList<T> {
+        PyTypedList::from_list(PyList::new(py, &[]))
+    }
+
+    /// Creates a typed view of `list`.
+    ///
+    /// The items of the list are not checked; an item of the wrong type results in an
+    /// error when it is accessed.
+    #[inline]
+    pub fn from_list(list: PyList) -> PyTypedList<T> {
+        PyTypedList { list, marker: PhantomData }
+    }
+
+    /// Gets the underlying list.
+    #[inline]
+    pub fn as_list(&self) -> &PyList {
+        &self.list
+    }
+
+    /// Gets the underlying list.
+    #[inline]
+    pub fn into_list(self) -> PyList {
+        self.list
+    }
+
+    /// Gets the length of the list.
+    #[inline]
+    pub fn len(&self, py: Python) -> usize {
+        self.list.len(py)
+    }
+
+    /// Returns true if the list is empty.
+    #[inline]
+    pub fn is_empty(&self, py: Python) -> bool {
+        self.list.len(py) == 0
+    }
+}
+
+impl <T> PyTypedList<T> where T: for<'s> FromPyObject<'s> {
+    /// Gets the item at the specified index, converted to `T`.
+    ///
+    /// Raises `IndexError` if the index is out of range.
+    pub fn get_item(&self, py: Python, index: usize) -> PyResult<T> {
+        if index >= self.list.len(py) {
+            return Err(PyErr::new::<exc::IndexError, _>(py, "list index out of range"));
+        }
+        self.list.get_item(py, index).extract(py)
+    }
+
+    /// Returns an iterator over the items of the list, converted to `T`.
+    #[inline]
+    pub fn iter<'a, 'p>(&'a self, py: Python<'p>) -> PyTypedListIterator<'a, 'p, T> {
+        PyTypedListIterator { py, list: self, index: 0 }
+    }
+}
+
+impl <T> PyTypedList<T> where T: ToPyObject {
+    /// Sets the item at the specified index.
+    ///
+    /// Raises `IndexError` if the index is out of range.
+    pub fn set_item(&self, py: Python, index: usize, item: &T) -> PyResult<()> {
+        if index >= self.list.len(py) {
+            return Err(PyErr::new::<exc::IndexError, _>(py, "list assignment index out of range"));
+        }
+        self.list.set_item(py, index, item.to_py_object(py).into_object());
+        Ok(())
+    }
+
+    /// Appends an item to the end of the list.
+    pub fn append(&self, py: Python, item: &T) -> PyResult<()> {
+        item.with_borrowed_ptr(py, |item| unsafe {
+            err::error_on_minusone(py, ffi::PyList_Append(self.list.as_object().as_ptr(), item))
+        })
+    }
+}
+
+impl <T> PyClone for PyTypedList<T> {
+    #[inline]
+    fn clone_ref(&self, py: Python) -> PyTypedList<T> {
+        PyTypedList::from_list(self.list.clone_ref(py))
+    }
+}
+
+impl <T> ToPyObject for PyTypedList<T> {
+    type ObjectType = PyList;
+
+    #[inline]
+    fn to_py_object(&self, py: Python) -> PyList {
+        self.list.clone_ref(py)
+    }
+
+    #[inline]
+    fn into_py_object(self, _py: Python) -> PyList {
+        self.list
+    }
+}
+
+/// Fails with `TypeError` if the input is not a Python `list`.
+/// The items are not checked.
+impl <'s, T> FromPyObject<'s> for PyTypedList<T> {
+    fn extract(py: Python, obj: &'s PyObject) -> PyResult<PyTypedList<T>> {
+        Ok(PyTypedList::from_list(obj.cast_as::<PyList>(py)?.clone_ref(py)))
+    }
+}
+
+/// Iterates over the items of the list, converted to `T`.
+///
+/// The iterator acquires the GIL for each item;
+/// use `PyTypedList::iter()` when the GIL is already held.
+impl <T> IntoIterator for PyTypedList<T> where T: for<'s> FromPyObject<'s> {
+    type Item = PyResult<T>;
+    type IntoIter = PyTypedListIntoIter<T>;
+
+    fn into_iter(self) -> PyTypedListIntoIter<T> {
+        PyTypedListIntoIter { list: self, index: 0 }
+    }
+}
+
+/// Used by `PyTypedList::iter()`.
+pub struct PyTypedListIterator<'a, 'p, T: 'a> {
+    py: Python<'p>,
+    list: &'a PyTypedList<T>,
+    index: usize,
+}
+
+impl <'a, 'p, T> Iterator for PyTypedListIterator<'a, 'p, T> where T: for<'s> FromPyObject<'s> {
+    type Item = PyResult<T>;
+
+    #[inline]
+    fn next(&mut self) -> Option<PyResult<T>> {
+        if self.index < self.list.len(self.py) {
+            let item = self.list.get_item(self.py, self.index);
+            self.index += 1;
+            Some(item)
+        } else {
+            None
+        }
+    }
+}
+
+/// Used by `PyTypedList::into_iter()`.
+pub struct PyTypedListIntoIter<T> {
+    list: PyTypedList<T>,
+    index: usize,
+}
+
+impl <T> Iterator for PyTypedListIntoIter<T> where T: for<'s> FromPyObject<'s> {
+    type Item = PyResult<T>;
+
+    fn next(&mut self) -> Option<PyResult<T>> {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        if self.index < self.list.len(py) {
+            let item = self.list.get_item(py, self.index);
+            self.index += 1;
+            Some(item)
+        } else {
+            None
+        }
+    }
+}
+
+/// A Python `dict` with keys of type `K` and values of type `V`.
+///
+/// The keys and values are converted from (or to) `K` and `V` when they are accessed.
+pub struct PyTypedDict<K, V> {
+    dict: PyDict,
+    marker: PhantomData<fn() -> (K, V)>,
+}
+
+impl <K, V> PyTypedDict<K, V> {
+    /// Creates a new, empty dictionary.
+    pub fn new(py: Python) -> PyTypedDict<K, V> {
+        PyTypedDict::from_dict(PyDict::new(py))
+    }
+
+    /// Creates a typed view of `dict`.
+    ///
+    /// The items of the dictionary are not checked; a key or a value of the wrong type
+    /// results in an error when it is accessed.
+    #[inline]
+    pub fn from_dict(dict: PyDict) -> PyTypedDict<K, V> {
+        PyTypedDict { dict, marker: PhantomData }
+    }
+
+    /// Gets the underlying dictionary.
+    #[inline]
+    pub fn as_dict(&self) -> &PyDict {
+        &self.dict
+    }
+
+    /// Gets the underlying dictionary.
+    #[inline]
+    pub fn into_dict(self) -> PyDict {
+        self.dict
+    }
+
+    /// Return the number of items in the dictionary.
+    #[inline]
+    pub fn len(&self, py: Python) -> usize {
+        self.dict.len(py)
+    }
+
+    /// Returns true if the dictionary is empty.
+    #[inline]
+    pub fn is_empty(&self, py: Python) -> bool {
+        self.dict.len(py) == 0
+    }
+
+    /// Return true if the dictionary contains `key`.
+    ///
+    /// The key is a `ToPyObject` rather than a `K`, so that e.g. a `&str` can be used
+    /// to look up a `String` key.
+    #[inline]
+    pub fn contains<Q>(&self, py: Python, key: Q) -> PyResult<bool> where Q: ToPyObject {
+        self.dict.contains(py, key)
+    }
+
+    /// Deletes an item.
+    #[inline]
+    pub fn del_item<Q>(&self, py: Python, key: Q) -> PyResult<()> where Q: ToPyObject {
+        self.dict.del_item(py, key)
+    }
+}
+
+impl <K, V> PyTypedDict<K, V> where V: for<'s> FromPyObject<'s> {
+    /// Gets the value for `key`, converted to `V`.
+    /// Returns `None` if the dictionary doesn't contain `key`.
+    pub fn get_item<Q>(&self, py: Python, key: Q) -> PyResult<Option<V>> where Q: ToPyObject {
+        match self.dict.get_item(py, key) {
+            Some(value) => value.extract(py).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl <K, V> PyTypedDict<K, V> where K: for<'s> FromPyObject<'s>, V: for<'s> FromPyObject<'s> {
+    /// Returns an iterator over the items of the dictionary, converted to `(K, V)`.
+    ///
+    /// The iterator works on a snapshot of the items, taken when it is created.
+    pub fn iter<'p>(&self, py: Python<'p>) -> PyTypedDictIterator<'p, K, V> {
+        PyTypedDictIterator { py, items: self.dict.items(py).into_iter(), marker: PhantomData }
+    }
+}
+
+impl <K, V> PyTypedDict<K, V> where K: ToPyObject, V: ToPyObject {
+    /// Sets an item value.
+    #[inline]
+    pub fn set_item(&self, py: Python, key: &K, value: &V) -> PyResult<()> {
+        self.dict.set_item(py, key, value)
+    }
+}
+
+impl <K, V> PyClone for PyTypedDict<K, V> {
+    #[inline]
+    fn clone_ref(&self, py: Python) -> PyTypedDict<K, V> {
+        PyTypedDict::from_dict(self.dict.clone_ref(py))
+    }
+}
+
+impl <K, V> ToPyObject for PyTypedDict<K, V> {
+    type ObjectType = PyDict;
+
+    #[inline]
+    fn to_py_object(&self, py: Python) -> PyDict {
+        self.dict.clone_ref(py)
+    }
+
+    #[inline]
+    fn into_py_object(self, _py: Python) -> PyDict {
+        self.dict
+    }
+}
+
+/// Fails with `TypeError` if the input is not a Python `dict`.
+/// The items are not checked.
+impl <'s, K, V> FromPyObject<'s> for PyTypedDict<K, V> {
+    fn extract(py: Python, obj: &'s PyObject) -> PyResult<PyTypedDict<K, V>> {
+        Ok(PyTypedDict::from_dict(obj.cast_as::<PyDict>(py)?.clone_ref(py)))
+    }
+}
+
+/// Iterates over the items of the dictionary, converted to `(K, V)`.
+///
+/// The iterator acquires the GIL for each item;
+/// use `PyTypedDict::iter()` when the GIL is already held.
+impl <K, V> IntoIterator for PyTypedDict<K, V>
+    where K: for<'s> FromPyObject<'s>, V: for<'s> FromPyObject<'s>
+{
+    type Item = PyResult<(K, V)>;
+    type IntoIter = PyTypedDictIntoIter<K, V>;
+
+    fn into_iter(self) -> PyTypedDictIntoIter<K, V> {
+        let gil = Python::acquire_gil();
+        let items = self.dict.items(gil.python()).into_iter();
+        PyTypedDictIntoIter { items, marker: PhantomData }
+    }
+}
+
+fn extract_item<K, V>(py: Python, (key, value): (PyObject, PyObject)) -> PyResult<(K, V)>
+    where K: for<'s> FromPyObject<'s>, V: for<'s> FromPyObject<'s>
+{
+    Ok((key.extract(py)?, value.extract(py)?))
+}
+
+/// Used by `PyTypedDict::iter()`.
+pub struct PyTypedDictIterator<'p, K, V> {
+    py: Python<'p>,
+    items: vec::IntoIter<(PyObject, PyObject)>,
+    marker: PhantomData<fn() -> (K, V)>,
+}
+
+impl <'p, K, V> Iterator for PyTypedDictIterator<'p, K, V>
+    where K: for<'s> FromPyObject<'s>, V: for<'s> FromPyObject<'s>
+{
+    type Item = PyResult<(K, V)>;
+
+    #[inline]
+    fn next(&mut self) -> Option<PyResult<(K, V)>> {
+        let py = self.py;
+        self.items.next().map(|item| extract_item(py, item))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.items.size_hint()
+    }
+}
+
+/// Used by `PyTypedDict::into_iter()`.
+pub struct PyTypedDictIntoIter<K, V> {
+    items: vec::IntoIter<(PyObject, PyObject)>,
+    marker: PhantomData<fn() -> (K, V)>,
+}
+
+impl <K, V> Iterator for PyTypedDictIntoIter<K, V>
+    where K: for<'s> FromPyObject<'s>, V: for<'s> FromPyObject<'s>
+{
+    type Item = PyResult<(K, V)>;
+
+    fn next(&mut self) -> Option<PyResult<(K, V)>> {
+        let gil = Python::acquire_gil();
+        self.items.next().map(|item| extract_item(gil.python(), item))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.items.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use python::{Python, PythonObject};
+    use objects::{PyList, PyDict};
+    use exc;
+    use super::{PyTypedList, PyTypedDict};
+
+    #[test]
+    fn test_typed_list() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let list: PyTypedList<String> = PyTypedList::new(py);
+        assert!(list.is_empty(py));
+        list.append(py, &"a".to_owned()).unwrap();
+        list.append(py, &"b".to_owned()).unwrap();
+        list.set_item(py, 1, &"c".to_owned()).unwrap();
+        assert!(list.set_item(py, 2, &"d".to_owned()).is_err());
+        assert_eq!(list.get_item(py, 0).unwrap(), "a");
+        assert!(list.get_item(py, 2).unwrap_err().matches(py, py.get_type::<exc::IndexError>()));
+        let items: Vec<String> = list.iter(py).map(|item| item.unwrap()).collect();
+        assert_eq!(items, vec!["a", "c"]);
+
+        let list = py.eval("[1, 'two', 3]", None, None).unwrap().extract::<PyTypedList<i32>>(py).unwrap();
+        let items: Vec<_> = list.into_iter().collect();
+        assert_eq!(items[0].as_ref().unwrap(), &1);
+        assert!(items[1].is_err());
+        assert_eq!(items[2].as_ref().unwrap(), &3);
+        assert!(py.eval("(1, 2)", None, None).unwrap().extract::<PyTypedList<i32>>(py).is_err());
+    }
+
+    #[test]
+    fn test_typed_dict() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let dict: PyTypedDict<String, i32> = PyTypedDict::new(py);
+        dict.set_item(py, &"a".to_owned(), &1).unwrap();
+        dict.set_item(py, &"b".to_owned(), &2).unwrap();
+        assert_eq!(dict.len(py), 2);
+        assert_eq!(dict.get_item(py, "a").unwrap(), Some(1));
+        assert_eq!(dict.get_item(py, "c").unwrap(), None);
+        assert!(dict.contains(py, "b").unwrap());
+        dict.del_item(py, "b").unwrap();
+        let py_dict: PyDict = dict.into_dict();
+        py_dict.set_item(py, "z", "not an int").unwrap();
+
+        let dict = py_dict.into_object().extract::<PyTypedDict<String, i32>>(py).unwrap();
+        let mut items: Vec<_> = dict.iter(py).collect();
+        assert_eq!(items.len(), 2);
+        assert!(items.pop().unwrap().is_err());
+        assert_eq!(items.pop().unwrap().unwrap(), ("a".to_owned(), 1));
+        let items: Vec<_> = dict.into_iter().filter_map(Result::ok).collect();
+        assert_eq!(items, vec![("a".to_owned(), 1)]);
+        let list = PyList::new(py, &[]).into_object();
+        assert!(list.extract::<PyTypedDict<String, i32>>(py).is_err());
+    }
+}
@@ -16,7 +16,7 @@
 // OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use python::{Python, PythonObject, ToPythonPointer};
+use python::{Python, PythonObject, PythonObjectWithTypeObject, ToPythonPointer};
 use conversion::ToPyObject;
 use objects::{PyObject, PyTuple, PyDict};
 use err::{PyResult, result_from_owned_ptr};
@@ -57,6 +57,14 @@ impl PyType {
         unsafe { ffi::PyType_IsSubtype(self.as_type_ptr(), b.as_type_ptr()) != 0 }
     }
 
+    /// Return true if `self` is `T` or a subtype of `T`.
+    #[inline]
+    pub fn is_subclass_of<T>(&self, py: Python) -> bool
+        where T: PythonObjectWithTypeObject
+    {
+        self.is_subtype_of(py, &T::type_object(py))
+    }
+
     /// Return true if `obj` is an instance of `self`.
     #[inline]
     pub fn is_instance(&self, _: Python, obj : &PyObject) -> bool {
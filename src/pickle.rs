@@ -0,0 +1,207 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Serialization of Python objects using the `pickle` module.
+//!
+//! ```
+//! use cpython::{Python, PyDict, PythonObject};
+//! use cpython::pickle::{self, Protocol};
+//!
+//! let gil = Python::acquire_gil();
+//! let py = gil.python();
+//! let dict = PyDict::new(py);
+//! dict.set_item(py, "answer", 42).unwrap();
+//!
+//! let data: Vec<u8> = pickle::dumps(py, dict.as_object(), Protocol::Highest).unwrap();
+//! let obj = pickle::loads(py, &data).unwrap();
+//! assert_eq!(obj.cast_into::<PyDict>(py).unwrap().len(py), 1);
+//! ```
+
+use conversion::ToPyObject;
+use err::{PyErr, PyResult};
+use exc;
+use objects::{PyObject, PyBytes, PyModule};
+use python::{Python, PythonObject};
+
+/// The pickle protocol version used by `dumps()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    /// `pickle.DEFAULT_PROTOCOL`
+    Default,
+    /// `pickle.HIGHEST_PROTOCOL`
+    Highest,
+    /// An explicit protocol version.
+    Version(u8),
+}
+
+/// Error returned by `dumps()` and `loads()`.
+#[derive(Debug)]
+pub enum PickleError {
+    /// The object cannot be pickled.
+    ///
+    /// Wraps a `pickle.PicklingError`, or a `TypeError`/`AttributeError` raised by the pickler
+    /// for objects that don't support pickling.
+    Pickling(PyErr),
+    /// The data is not a valid pickle.
+    ///
+    /// Wraps a `pickle.UnpicklingError`, or the `EOFError` raised for truncated data.
+    Unpickling(PyErr),
+    /// Any other Python exception, e.g. raised by `__reduce__` or `__setstate__`,
+    /// or raised while importing the class of an unpickled object.
+    Python(PyErr),
+}
+
+impl From<PickleError> for PyErr {
+    fn from(e: PickleError) -> PyErr {
+        match e {
+            PickleError::Pickling(e) | PickleError::Unpickling(e) | PickleError::Python(e) => e
+        }
+    }
+}
+
+impl From<PyErr> for PickleError {
+    fn from(e: PyErr) -> PickleError {
+        PickleError::Python(e)
+    }
+}
+
+#[cfg(feature="python27-sys")]
+fn pickle_module(py: Python) -> PyResult<PyModule> {
+    py.import("cPickle").or_else(|_| py.import("pickle"))
+}
+
+#[cfg(feature="python3-sys")]
+fn pickle_module(py: Python) -> PyResult<PyModule> {
+    py.import("pickle")
+}
+
+/// Serializes `obj` using `pickle.dumps()`.
+pub fn dumps(py: Python, obj: &PyObject, protocol: Protocol) -> Result<Vec<u8>, PickleError> {
+    let pickle = pickle_module(py)?;
+    // cPickle.dumps() on Python 2 doesn't accept `protocol=None`, so the default protocol
+    // is selected by leaving out the argument.
+    let protocol = match protocol {
+        Protocol::Default => None,
+        Protocol::Highest => Some(pickle.get(py, "HIGHEST_PROTOCOL")?),
+        Protocol::Version(v) => {
+            let highest: u8 = pickle.get(py, "HIGHEST_PROTOCOL")?.extract(py)?;
+            if v > highest {
+                return Err(PickleError::Python(PyErr::new::<exc::ValueError, _>(py,
+                    format!("pickle protocol must be <= {}", highest))));
+            }
+            Some(v.to_py_object(py).into_object())
+        }
+    };
+    let result = match protocol {
+        Some(protocol) => pickle.call(py, "dumps", (obj, protocol), None),
+        None => pickle.call(py, "dumps", (obj,), None),
+    };
+    match result {
+        Ok(data) => Ok(data.cast_into::<PyBytes>(py).map_err(PyErr::from)?.data(py).to_vec()),
+        Err(e) => {
+            let pickling_error = pickle.get(py, "PicklingError")?;
+            if e.matches(py, (pickling_error, py.get_type::<exc::TypeError>(),
+                              py.get_type::<exc::AttributeError>())) {
+                Err(PickleError::Pickling(e))
+            } else {
+                Err(PickleError::Python(e))
+            }
+        }
+    }
+}
+
+/// Deserializes an object using `pickle.loads()`.
+///
+/// Only unpickle trusted data: unpickling can execute arbitrary code.
+pub fn loads(py: Python, data: &[u8]) -> Result<PyObject, PickleError> {
+    let pickle = pickle_module(py)?;
+    match pickle.call(py, "loads", (PyBytes::new(py, data),), None) {
+        Ok(obj) => Ok(obj),
+        Err(e) => {
+            let unpickling_error = pickle.get(py, "UnpicklingError")?;
+            if e.matches(py, (unpickling_error, py.get_type::<exc::EOFError>())) {
+                Err(PickleError::Unpickling(e))
+            } else {
+                Err(PickleError::Python(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use python::{Python, PythonObject};
+    use conversion::ToPyObject;
+    use objectprotocol::ObjectProtocol;
+    use objects::PyList;
+    use super::{dumps, loads, Protocol, PickleError};
+
+    #[test]
+    fn test_roundtrip() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let obj = py.eval("{'a': [1, 2.5, None], 'b': (u'x', b'y')}", None, None).unwrap();
+        for &protocol in &[Protocol::Default, Protocol::Highest, Protocol::Version(2)] {
+            let data = dumps(py, &obj, protocol).unwrap();
+            let copy = loads(py, &data).unwrap();
+            assert!(copy.rich_compare(py, &obj, ::CompareOp::Eq).unwrap().is_true(py).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_default_protocol() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let list = PyList::new(py, &[1i32.to_py_object(py).into_object(), py.None()]);
+        let data = dumps(py, list.as_object(), Protocol::Default).unwrap();
+        let copy = loads(py, &data).unwrap().cast_into::<PyList>(py).unwrap();
+        assert_eq!(copy.len(py), 2);
+        assert_eq!(copy.get_item(py, 0).extract::<i32>(py).unwrap(), 1);
+        assert!(copy.get_item(py, 1) == py.None());
+    }
+
+    #[test]
+    fn test_pickling_error() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let obj = py.eval("lambda: None", None, None).unwrap();
+        match dumps(py, &obj, Protocol::Default) {
+            Err(PickleError::Pickling(_)) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+        match dumps(py, &42i32.to_py_object(py).into_object(), Protocol::Version(255)) {
+            Err(PickleError::Python(ref e)) if e.matches(py, py.get_type::<::exc::ValueError>()) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_unpickling_error() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        match loads(py, b"not a pickle") {
+            Err(PickleError::Unpickling(_)) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+        let data = dumps(py, &py.True().into_object(), Protocol::Highest).unwrap();
+        match loads(py, &data[..data.len() - 1]) {
+            Err(PickleError::Unpickling(_)) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+}
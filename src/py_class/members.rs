@@ -89,7 +89,8 @@ macro_rules! py_class_instance_method {
                 })
         }
         unsafe {
-            let method_def = py_method_def!(stringify!($f), 0, wrap_instance_method);
+            let method_def = py_method_def!(stringify!($f), 0, wrap_instance_method,
+                py_text_signature!(stringify!($f), { "$self" } [ $( { $pname : $ptype = $detail } )* ]));
             $crate::py_class::members::create_instance_method_descriptor::<$class>(method_def)
         }
     }}
@@ -138,7 +139,8 @@ macro_rules! py_class_class_method {
         unsafe {
             let method_def = py_method_def!(stringify!($f),
                 $crate::_detail::ffi::METH_CLASS,
-                wrap_class_method);
+                wrap_class_method,
+                py_text_signature!(stringify!($f), { "$type" } [ $( { $pname : $ptype = $detail } )* ]));
             $crate::py_class::members::create_class_method_descriptor(method_def)
         }
     }}
@@ -185,7 +187,8 @@ macro_rules! py_class_static_method {
         unsafe {
             let method_def = py_method_def!(stringify!($f),
                 $crate::_detail::ffi::METH_STATIC,
-                wrap_static_method);
+                wrap_static_method,
+                py_text_signature!(stringify!($f), { } [ $( { $pname : $ptype = $detail } )* ]));
             $crate::_detail::py_fn_impl($py, method_def)
         }
     }}
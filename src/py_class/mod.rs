@@ -16,18 +16,18 @@
 // OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-mod py_class;
+#[macro_use] mod py_class;
 #[cfg(feature="python27-sys")]
-mod py_class_impl2;
+#[macro_use] mod py_class_impl2;
 #[cfg(feature="python3-sys")]
-mod py_class_impl3;
-#[doc(hidden)] pub mod slots;
-#[doc(hidden)] pub mod members;
-pub mod gc;
+#[macro_use] mod py_class_impl3;
+#[doc(hidden)] #[macro_use] pub mod slots;
+#[doc(hidden)] #[macro_use] pub mod members;
+#[macro_use] pub mod gc;
 
 use libc;
 use std::{mem, ptr, cell};
-use python::{self, Python, PythonObject};
+use python::{self, Python, PythonObject, ToPythonPointer};
 use objects::{PyObject, PyType, PyModule};
 use err::{self, PyResult};
 use ffi;
@@ -56,6 +56,49 @@ pub trait PythonObjectFromPyClassMacro : python::PythonObjectWithTypeObject {
     fn add_to_module(py: Python, module: &PyModule) -> PyResult<()>;
 }
 
+/// Implemented by the types of the data members copied by the `copy_data()` method
+/// of `py_class!()` classes, i.e. by the types implementing `Clone`.
+///
+/// This is an unstable implementation detail; do not implement manually!
+#[doc(hidden)]
+pub trait CopyData<'a>: Sized {
+    fn copy_data(&'a self) -> Self;
+}
+
+impl <'a, T> CopyData<'a> for T where T: Clone {
+    #[inline]
+    fn copy_data(&'a self) -> T {
+        self.clone()
+    }
+}
+
+/// Gets the size of `obj` in memory, in bytes, plus `heap_size`, for implementing `__sizeof__`.
+///
+/// The size of the object includes its data fields (`tp_basicsize` of its type), but not
+/// the memory they own on the heap, which is given by `heap_size`.
+///
+/// ```
+/// #[macro_use] extern crate cpython;
+/// use std::mem;
+/// use cpython::{PyResult, py_class};
+///
+/// py_class!(class Buffer |py| {
+///     data content: Vec<u64>;
+///
+///     def __sizeof__(&self) -> PyResult<usize> {
+///         let heap_size = self.content(py).capacity() * mem::size_of::<u64>();
+///         Ok(py_class::instance_size(py, self, heap_size))
+///     }
+/// });
+/// # fn main() {}
+/// ```
+pub fn instance_size<T: PythonObject>(_py: Python, obj: &T, heap_size: usize) -> usize {
+    unsafe {
+        let ty = ffi::Py_TYPE(obj.as_object().as_ptr());
+        (*ty).tp_basicsize as usize + heap_size
+    }
+}
+
 #[inline]
 #[doc(hidden)]
 pub fn data_offset<T>(base_size: usize) -> usize {
@@ -71,6 +71,7 @@ impl PythonObjectFromPyClassMacro for MyType { ... }
 
 impl MyType {
     fn create_instance(py: Python, number: i32) -> PyResult<MyType> { ... }
+    fn copy_data(&self, py: Python) -> PyResult<MyType> { ... }
 
     // data accessors
     fn number<'a>(&'a self, py: Python<'a>) -> &'a i32 { ... }
@@ -90,6 +91,8 @@ impl MyType {
 * The generated type implements a number of traits from the `cpython` crate.
 * The inherent `create_instance` method can create new Python objects
   given the values for the data fields.
+* If all the data fields implement `Clone`, the inherent `copy_data` method creates
+  a new Python object with clones of the data fields (see `__copy__` below).
 * Private accessors functions are created for the data fields.
 * All functions callable from Python are also exposed as public Rust functions.
 * To convert from `MyType` to `PyObject`, use `as_object()` or `into_object()` (from the `PythonObject` trait).
@@ -109,7 +112,13 @@ Because Python code can pass all Python objects to other threads,
 
 Because Python object instances can be freely shared (Python has no concept of "ownership"),
 data fields cannot be declared as `mut`.
-If mutability is required, you have to use interior mutability (`Cell` or `RefCell`).
+If mutability is required, you have to use interior mutability, e.g. `Cell` or `PyRefCell`.
+Methods of the class can be called again by Python code while the data is borrowed
+(e.g. from a callback or from the `__eq__` method of another object).
+`PyRefCell` raises `RuntimeError` on such a conflicting borrow, while a plain `RefCell`
+panics: the accessors generated for `data` members return the member as is, so the
+borrow checks are those of the member type.
+Prefer `PyRefCell` to `RefCell` unless the data is never borrowed while running Python code.
 
 If data members are used to store references to other Python objects, make sure
 to read the section "Garbage Collector Integration".
@@ -151,6 +160,10 @@ Declares a static method callable from Python.
 * For details on `parameter-list`, see the documentation of `py_argparse!()`.
 * The return type must be `PyResult<T>` for some `T` that implements `ToPyObject`.
 
+On Python 3, instance, class and static methods have a `__text_signature__`, used by
+`inspect.signature()`. Default values that are literals, or `true`, `false` or `None`,
+are part of the signature; if any default value is another expression, the method has no signature.
+
 ## __new__
 `def __new__(cls, parameter-list) -> PyResult<...> { ... }`
 
@@ -275,9 +288,16 @@ py_class!(class MyIterator |py| {
 
   * `def __format__(&self, format_spec: &str) -> PyResult<impl ToPyObject<ObjectType=PyString>>`
 
-    Special method that is used by the `format()` builtin and the `str.format()` method.
+    Special method that is used by the `format()` builtin, the `str.format()` method and f-strings.
     Possible return types are `PyResult<String>` or `PyResult<PyString>`.
 
+  * `def __fspath__(&self) -> PyResult<impl ToPyObject>`
+
+    Makes the object a path-like object, which can be passed to `open()` and to the functions
+    of the `os` module. The return type should be `PyResult<String>` or `PyResult<PyBytes>`.
+
+    `__fspath__` is new in Python 3.6; older versions will ignore the method.
+
 ## Comparison operators
 
   * `def __richcmp__(&self, other: impl FromPyObject, op: CompareOp) -> PyResult<impl ToPyObject>`
@@ -381,6 +401,77 @@ py_class!(class MyIterator |py| {
   * `def __enter__(&self) -> PyResult<impl ToPyObject>`
   * `def __exit__(&self, ty: Option<PyType>, value: PyObject, traceback: PyObject) -> PyResult<bool>`
 
+## Copying
+
+  * `def __copy__(&self) -> PyResult<impl ToPyObject>`
+  * `def __deepcopy__(&self, memo: PyDict) -> PyResult<impl ToPyObject>`
+
+    Called by `copy.copy()` and `copy.deepcopy()`. Without these methods, the `copy` module
+    falls back to the pickle protocol, which doesn't know about the data fields.
+
+    When all the data fields implement `Clone`, both methods can be implemented with `copy_data`:
+
+```
+#[macro_use] extern crate cpython;
+use cpython::{PyDict, PyResult};
+
+py_class!(class Point |py| {
+    data x: i32;
+    data y: i32;
+
+    def __copy__(&self) -> PyResult<Point> {
+        self.copy_data(py)
+    }
+
+    def __deepcopy__(&self, _memo: PyDict) -> PyResult<Point> {
+        self.copy_data(py)
+    }
+});
+# fn main() {}
+```
+
+`copy_data` isn't available when a data field holds Python objects, because `PyObject`
+implements `PyClone` instead of `Clone`. Such classes implement the methods by hand:
+`__copy__` shares the objects with `clone_ref`, and `__deepcopy__` copies them
+with `copy.deepcopy(obj, memo)`.
+
+```
+#[macro_use] extern crate cpython;
+use cpython::{PyDict, PyObject, PyResult, PyClone};
+
+py_class!(class Node |py| {
+    data value: PyObject;
+
+    def __copy__(&self) -> PyResult<Node> {
+        Node::create_instance(py, self.value(py).clone_ref(py))
+    }
+
+    def __deepcopy__(&self, memo: PyDict) -> PyResult<Node> {
+        let value = py.import("copy")?.call(py, "deepcopy", (self.value(py), memo), None)?;
+        Node::create_instance(py, value)
+    }
+});
+# fn main() {}
+```
+
+## Rich Display in IPython/Jupyter
+
+IPython and Jupyter notebooks look for methods like `_repr_html_`, `_repr_markdown_`,
+`_repr_latex_`, `_repr_svg_`, `_repr_png_`, `_repr_jpeg_`, `_repr_json_`, `_repr_mimebundle_`
+and `_ipython_display_`.
+These are not special methods for Python, so they're declared like any other instance method:
+
+  * `def _repr_html_(&self) -> PyResult<String>`
+
+    `Ok(None)` (with a `PyResult<Option<String>>` return type) tells IPython that
+    the representation is not available; IPython then falls back to other representations.
+
+  * `def _repr_png_(&self) -> PyResult<PyBytes>`
+
+  * `def _repr_mimebundle_(&self, include: Option<PyObject> = None, exclude: Option<PyObject> = None) -> PyResult<cpython::display::MimeBundle>`
+
+    Returns several representations at once; see the `display` module.
+
 ## Other Special Methods
 
   * `def __bool__(&self) -> PyResult<bool>`
@@ -390,6 +481,12 @@ py_class!(class MyIterator |py| {
     Note that `py_class!` always expects this member to be called `__bool__`,
     even on Python 2.7 where the Python spelling was `__nonzero__`.
 
+  * `def __sizeof__(&self) -> PyResult<usize>`
+
+    Called by `sys.getsizeof()` and memory profilers. By default, the size of an instance
+    includes its data fields, but not the memory they own on the heap;
+    `cpython::py_class::instance_size()` helps reporting it.
+
   * `def __call__(&self, parameter-list) -> PyResult<impl ToPyObject>`
 
     For details on `parameter-list`, see the documentation of `py_argparse!()`.
@@ -193,6 +193,14 @@ base_case = '''
                         }
                     }
                 }
+
+                // The bounds mention 'a so that they are only checked when copy_data() is used.
+                #[allow(dead_code)]
+                fn copy_data<'a>(&'a self, py: $crate::Python<'a>) -> $crate::PyResult<$class>
+                    where $( $data_ty: $crate::py_class::CopyData<'a> ),*
+                {
+                    $class::create_instance(py $( , $crate::py_class::CopyData::copy_data(self.$data_name(py)) )*)
+                }
             }
         }
     };
@@ -613,6 +621,10 @@ special_names = {
     '__unicode__': normal_method(),
     '__bytes__': normal_method(),
     '__format__': normal_method(),
+    '__fspath__': normal_method(),
+    '__sizeof__': normal_method(),
+    '__copy__': normal_method(),
+    '__deepcopy__': normal_method(),
     # Comparison Operators
     '__lt__': error('__lt__ is not supported by py_class! use __richcmp__ instead.'),
     '__le__': error('__le__ is not supported by py_class! use __richcmp__ instead.'),
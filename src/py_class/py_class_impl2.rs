@@ -185,6 +185,14 @@ macro_rules! py_class_impl {
                         }
                     }
                 }
+
+                // The bounds mention 'a so that they are only checked when copy_data() is used.
+                #[allow(dead_code)]
+                fn copy_data<'a>(&'a self, py: $crate::Python<'a>) -> $crate::PyResult<$class>
+                    where $( $data_ty: $crate::py_class::CopyData<'a> ),*
+                {
+                    $class::create_instance(py $( , $crate::py_class::CopyData::copy_data(self.$data_name(py)) )*)
+                }
             }
         }
     };
@@ -0,0 +1,143 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Moving Rust values into `py_class!` instances and back out of them.
+
+use std::cell::UnsafeCell;
+use std::ops::Deref;
+use err::{PyErr, PyResult};
+use exc;
+use python::{Python, PythonObject};
+
+/// A `py_class!` data member owning a boxed value, which can be moved back out of the
+/// Python object when no other reference to the object exists.
+///
+/// ```
+/// #[macro_use] extern crate cpython;
+/// use cpython::{Python, PyBox, PyResult};
+///
+/// py_class!(class Image |py| {
+///     data pixels: PyBox<Vec<u8>>;
+///
+///     def __len__(&self) -> PyResult<usize> {
+///         Ok(self.pixels(py).len())
+///     }
+/// });
+///
+/// fn main() {
+///     let gil = Python::acquire_gil();
+///     let py = gil.python();
+///     let image = Image::create_instance(py, PyBox::new(Box::new(vec![0; 1024]))).unwrap();
+///     // ... pass the image to Python code ...
+///     let pixels = match PyBox::into_inner(py, image, Image::pixels) {
+///         Ok(pixels) => pixels,
+///         Err(_image) => panic!("the image is still referenced from Python"),
+///     };
+///     assert_eq!(pixels.len(), 1024);
+/// }
+/// ```
+pub struct PyBox<T> {
+    // Only `None` while the owning Python object is being destroyed by `into_box()`.
+    value: UnsafeCell<Option<Box<T>>>,
+}
+
+unsafe impl <T: Send> Send for PyBox<T> {}
+
+impl <T> PyBox<T> {
+    /// Creates a new `PyBox`, to be moved into a Python object with `create_instance()`.
+    pub fn new(value: Box<T>) -> PyBox<T> {
+        PyBox { value: UnsafeCell::new(Some(value)) }
+    }
+
+    /// Moves the boxed value out of `obj`, consuming the object.
+    ///
+    /// `member` gets the `PyBox` data member from the object; it is typically the
+    /// accessor generated by `py_class!` for the data member (e.g. `MyClass::value`).
+    ///
+    /// Fails and returns `obj` if other references to the Python object exist,
+    /// which would still be able to access the value.
+    pub fn into_box<C, F>(py: Python, obj: C, member: F) -> Result<Box<T>, C>
+        where C: PythonObject, F: for<'a> FnOnce(&'a C, Python<'a>) -> &'a PyBox<T>
+    {
+        if obj.as_object().get_refcnt(py) != 1 {
+            return Err(obj);
+        }
+        let value = unsafe { (*member(&obj, py).value.get()).take() };
+        // `obj` is the only reference to the Python object, so nothing can access the
+        // empty `PyBox` before it is dropped together with the object.
+        drop(obj);
+        Ok(value.expect("PyBox value was already moved out"))
+    }
+
+    /// Moves the value out of `obj`, consuming the object.
+    ///
+    /// See `into_box()`.
+    pub fn into_inner<C, F>(py: Python, obj: C, member: F) -> Result<T, C>
+        where C: PythonObject, F: for<'a> FnOnce(&'a C, Python<'a>) -> &'a PyBox<T>
+    {
+        PyBox::into_box(py, obj, member).map(|value| *value)
+    }
+
+    /// Like `into_inner()`, but raises `RuntimeError` if other references to the object exist.
+    pub fn try_into_inner<C, F>(py: Python, obj: C, member: F) -> PyResult<T>
+        where C: PythonObject, F: for<'a> FnOnce(&'a C, Python<'a>) -> &'a PyBox<T>
+    {
+        PyBox::into_inner(py, obj, member).map_err(|_| {
+            PyErr::new::<exc::RuntimeError, _>(py, "object is still referenced; cannot move its data out")
+        })
+    }
+}
+
+impl <T> Deref for PyBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match unsafe { (*self.value.get()).as_ref() } {
+            Some(value) => value,
+            None => panic!("PyBox value was moved out"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use python::{Python, PythonObject, PyClone};
+    use objects::PyObject;
+    use super::PyBox;
+
+    py_class!(class Holder |py| {
+        data value: PyBox<String>;
+    });
+
+    #[test]
+    fn test_into_inner() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let holder = Holder::create_instance(py, PyBox::new(Box::new("value".to_owned()))).unwrap();
+        assert_eq!(&**holder.value(py), "value");
+
+        let other: PyObject = holder.clone_ref(py).into_object();
+        let holder = match PyBox::into_inner(py, holder, Holder::value) {
+            Ok(_) => panic!("moved the value out of a shared object"),
+            Err(holder) => holder,
+        };
+        assert!(PyBox::try_into_inner(py, holder.clone_ref(py), Holder::value).is_err());
+        drop(other);
+        assert_eq!(PyBox::try_into_inner(py, holder, Holder::value).unwrap(), "value");
+    }
+}
@@ -19,9 +19,12 @@
 use std;
 use std::ffi::CString;
 use std::marker::PhantomData;
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
 use libc::c_int;
 use ffi;
 use objects::{PyObject, PyType, PyBool, PyDict, PyModule};
+use objectprotocol::ObjectProtocol;
 use err::{self, PyErr, PyResult};
 use pythonrun::GILGuard;
 
@@ -104,6 +107,7 @@ pub trait PyDrop : Sized {
 impl <T> PyDrop for T where T: PythonObject {
     #[inline]
     fn release_ref(self, _py: Python) {
+        ::gilcheck::assert_gil_held();
         let ptr = self.into_object().steal_ptr();
         unsafe {
             ffi::Py_DECREF(ptr);
@@ -184,6 +188,7 @@ impl<'p> Python<'p> {
     /// when calling this function.
     #[inline]
     pub unsafe fn assume_gil_acquired() -> Python<'p> {
+        ::gilcheck::assert_gil_held();
         Python(PhantomData)
     }
 
@@ -292,6 +297,84 @@ impl<'p> Python<'p> {
         unsafe { PyObject::from_borrowed_ptr(self, ffi::Py_NotImplemented()) }
     }
 
+    /// Gets the Python builtin value `Ellipsis` (`...`).
+    #[allow(non_snake_case)] // the Python keyword starts with uppercase
+    #[inline]
+    pub fn Ellipsis(self) -> PyObject {
+        unsafe { PyObject::from_borrowed_ptr(self, ffi::Py_Ellipsis()) }
+    }
+
+    /// Depending on `val`, gets the Python builtin value `True` or `False`.
+    #[inline]
+    pub fn bool(self, val: bool) -> PyBool {
+        PyBool::get(self, val)
+    }
+
+    /// Gets the module holding the Python builtins
+    /// (`builtins` in Python 3, `__builtin__` in Python 2).
+    ///
+    /// The module is imported on the first call, and cached afterwards.
+    pub fn builtins(self) -> PyResult<PyModule> {
+        #[cfg(feature="python27-sys")]
+        const NAME: &str = "__builtin__";
+        #[cfg(feature="python3-sys")]
+        const NAME: &str = "builtins";
+        static mut BUILTINS: *mut ffi::PyObject = std::ptr::null_mut();
+
+        unsafe {
+            // The GIL protects the cache.
+            if BUILTINS.is_null() {
+                BUILTINS = self.import(NAME)?.into_object().steal_ptr();
+            }
+            Ok(PyObject::from_borrowed_ptr(self, BUILTINS).unchecked_cast_into::<PyModule>())
+        }
+    }
+
+    /// Gets the version of the running Python interpreter, from `sys.version_info`.
+    ///
+    /// ```
+    /// let gil = cpython::Python::acquire_gil();
+    /// let py = gil.python();
+    /// let version = py.version_info().unwrap();
+    /// if version >= (3, 7) {
+    ///     // e.g. use dataclasses
+    /// }
+    /// ```
+    pub fn version_info(self) -> PyResult<VersionInfo> {
+        // On Python 2, `sys.version_info` isn't a tuple, so read its attributes.
+        let info = self.import("sys")?.get(self, "version_info")?;
+        Ok(VersionInfo {
+            major: info.getattr(self, "major")?.extract(self)?,
+            minor: info.getattr(self, "minor")?.extract(self)?,
+            micro: info.getattr(self, "micro")?.extract(self)?,
+            releaselevel: info.getattr(self, "releaselevel")?.extract(self)?,
+            serial: info.getattr(self, "serial")?.extract(self)?,
+        })
+    }
+
+    /// Gets the version of the running Python interpreter as a single number,
+    /// from `sys.hexversion` (e.g. `0x030700f0` for 3.7.0 final).
+    pub fn hexversion(self) -> PyResult<u32> {
+        self.import("sys")?.get(self, "hexversion")?.extract(self)
+    }
+
+    /// Gets the name of the Python implementation, from `sys.implementation.name`
+    /// (e.g. `"cpython"` or `"pypy"`).
+    ///
+    /// Python 2 doesn't have `sys.implementation`; this returns `"cpython"` there.
+    pub fn implementation_name(self) -> PyResult<String> {
+        let sys = self.import("sys")?;
+        if !sys.as_object().hasattr(self, "implementation")? {
+            return Ok("cpython".to_owned());
+        }
+        sys.get(self, "implementation")?.getattr(self, "name")?.extract(self)
+    }
+
+    /// Gets the platform identifier, from `sys.platform` (e.g. `"linux"` or `"win32"`).
+    pub fn platform(self) -> PyResult<String> {
+        self.import("sys")?.get(self, "platform")?.extract(self)
+    }
+
     /// Gets the Python type object for type T.
     pub fn get_type<T>(self) -> PyType where T: PythonObjectWithTypeObject {
         T::type_object(self)
@@ -301,6 +384,94 @@ impl<'p> Python<'p> {
     pub fn import(self, name : &str) -> PyResult<PyModule> {
         PyModule::import(self, name)
     }
+
+    /// Import the Python module with the specified name, using a process-wide cache.
+    ///
+    /// The first call for a name imports the module; the following calls return the
+    /// same module object, without looking it up in `sys.modules`.
+    /// Use `clear_import_cache()` to release the cached modules,
+    /// e.g. if a test replaces a module in `sys.modules`.
+    pub fn import_cached(self, name: &str) -> PyResult<PyModule> {
+        let cached = IMPORT_CACHE.with(self, |map| {
+            map.as_ref().and_then(|map| map.get(name)).map(|module| module.clone_ref(self))
+        });
+        if let Some(module) = cached {
+            return Ok(module);
+        }
+        // The cache isn't borrowed while importing, since the module may use it.
+        // If the module was cached meanwhile (the import can release the GIL),
+        // the module cached first is kept.
+        let module = PyModule::import(self, name)?;
+        Ok(IMPORT_CACHE.with(self, |map| {
+            map.get_or_insert_with(HashMap::new)
+                .entry(name.to_owned())
+                .or_insert_with(|| module.clone_ref(self))
+                .clone_ref(self)
+        }))
+    }
+
+    /// Releases the modules cached by `import_cached()`.
+    pub fn clear_import_cache(self) {
+        // The modules are released after the cache is emptied, since releasing
+        // a module could run code using the cache.
+        let map = IMPORT_CACHE.with(self, Option::take);
+        drop(map);
+    }
+}
+
+/// Storage for `Python::import_cached()`. Only accessed with the GIL held.
+struct ImportCache(UnsafeCell<Option<HashMap<String, PyModule>>>);
+
+unsafe impl Sync for ImportCache { }
+
+static IMPORT_CACHE: ImportCache = ImportCache(UnsafeCell::new(None));
+
+impl ImportCache {
+    /// Calls `f` with the cached modules; `f` must not run Python code.
+    fn with<F, R>(&self, _py: Python, f: F) -> R
+        where F: FnOnce(&mut Option<HashMap<String, PyModule>>) -> R
+    {
+        f(unsafe { &mut *self.0.get() })
+    }
+}
+
+/// The version of a Python interpreter, as returned by `Python::version_info()`.
+///
+/// Versions are ordered like the Python `sys.version_info` tuples, and can be compared
+/// with `(major, minor)` tuples.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VersionInfo {
+    pub major: u8,
+    pub minor: u8,
+    pub micro: u8,
+    /// `"alpha"`, `"beta"`, `"candidate"` or `"final"`.
+    pub releaselevel: String,
+    pub serial: u8,
+}
+
+impl PartialEq<(u8, u8)> for VersionInfo {
+    fn eq(&self, other: &(u8, u8)) -> bool {
+        (self.major, self.minor) == *other
+    }
+}
+
+impl PartialOrd<(u8, u8)> for VersionInfo {
+    fn partial_cmp(&self, other: &(u8, u8)) -> Option<std::cmp::Ordering> {
+        (self.major, self.minor).partial_cmp(other)
+    }
+}
+
+impl std::fmt::Display for VersionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.micro)?;
+        match &self.releaselevel[..] {
+            "final" => Ok(()),
+            "alpha" => write!(f, "a{}", self.serial),
+            "beta" => write!(f, "b{}", self.serial),
+            "candidate" => write!(f, "rc{}", self.serial),
+            level => write!(f, "{}{}", level, self.serial),
+        }
+    }
 }
 
 impl <'p> std::fmt::Debug for PythonObjectDowncastError<'p> {
@@ -311,7 +482,7 @@ impl <'p> std::fmt::Debug for PythonObjectDowncastError<'p> {
 
 #[cfg(test)]
 mod test {
-    use {Python, PyDict};
+    use {Python, PythonObject, PyDict, PyModule, ObjectProtocol};
 
     #[test]
     fn test_eval() {
@@ -333,4 +504,57 @@ mod test {
         let v: i32 = py.eval("min(foo, 2)", None, Some(&d)).unwrap().extract(py).unwrap();
         assert_eq!(v, 2);
     }
+
+    #[test]
+    fn test_singletons() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        assert!(py.eval("Ellipsis", None, None).unwrap() == py.Ellipsis());
+        assert!(py.bool(true).is_true());
+        assert!(!py.bool(false).is_true());
+
+        let builtins = py.builtins().unwrap();
+        assert!(builtins.as_object() == py.builtins().unwrap().as_object());
+        let len = builtins.call(py, "len", ("abc",), None).unwrap();
+        assert_eq!(len.extract::<i32>(py).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_import_cached() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let json = py.import_cached("json").unwrap();
+        assert!(json.as_object() == py.import_cached("json").unwrap().as_object());
+        assert!(py.import_cached("no_such_module_for_import_cached").is_err());
+
+        let name = "rust_cpython_test_import_cached";
+        let modules = py.import("sys").unwrap().get(py, "modules").unwrap();
+        let first = PyModule::new(py, name).unwrap();
+        modules.set_item(py, name, &first).unwrap();
+        assert!(first.as_object() == py.import_cached(name).unwrap().as_object());
+        let second = PyModule::new(py, name).unwrap();
+        modules.set_item(py, name, &second).unwrap();
+        assert!(first.as_object() == py.import_cached(name).unwrap().as_object());
+        py.clear_import_cache();
+        assert!(second.as_object() == py.import_cached(name).unwrap().as_object());
+        modules.del_item(py, name).unwrap();
+    }
+
+    #[test]
+    fn test_version_info() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let version = py.version_info().unwrap();
+        let expected: String = py.eval("'%d.%d.%d' % __import__('sys').version_info[:3]", None, None)
+            .unwrap().extract(py).unwrap();
+        assert!(version.to_string().starts_with(&expected));
+        assert!(version >= (2, 7));
+        assert!(version < (version.major, version.minor + 1));
+        assert!(version == (version.major, version.minor));
+        let hex = py.hexversion().unwrap();
+        assert_eq!(hex >> 24, u32::from(version.major));
+        assert_eq!((hex >> 16) & 0xff, u32::from(version.minor));
+        assert_eq!(py.implementation_name().unwrap(), "cpython");
+        assert!(!py.platform().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,439 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Utilities for sharing the data of a `py_class!` with other Python objects.
+
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::ops::{Deref, DerefMut};
+use std::{mem, ptr};
+use err::{PyErr, PyResult};
+use exc;
+use objects::PyObject;
+use python::{Python, PyClone};
+
+/// The borrow state of a `PySharedRefCell`, shared with the `PyLeaked` references.
+#[derive(Debug, Default)]
+struct PySharedState {
+    /// Number of active borrows of leaked references (`PyLeakedRef` and `PyLeakedRefMut`).
+    borrow_count: Cell<usize>,
+    /// Incremented on each mutable borrow of the `PySharedRefCell`; leaked references
+    /// created before a mutable borrow become invalid.
+    generation: Cell<usize>,
+}
+
+impl PySharedState {
+    fn current_generation(&self, _py: Python) -> usize {
+        self.generation.get()
+    }
+
+    fn increment_generation(&self, _py: Python) {
+        self.generation.set(self.generation.get().wrapping_add(1));
+    }
+
+    fn increase_borrow_count(&self, _py: Python) {
+        self.borrow_count.set(self.borrow_count.get() + 1);
+    }
+
+    fn decrease_borrow_count(&self, _py: Python) {
+        let count = self.borrow_count.get();
+        assert!(count > 0, "borrow count underflow");
+        self.borrow_count.set(count - 1);
+    }
+}
+
+/// A `RefCell` whose content can be borrowed by other Python objects.
+///
+/// `PySharedRefCell` is intended to be used as `data` member of a `py_class!`,
+/// with the accessors generated by the `py_shared_ref!` macro.
+/// In addition to the usual `RefCell` borrows, the content can be *leaked* to other Python
+/// objects (e.g. iterators), which then keep the owner alive.
+///
+/// The content can still be borrowed mutably while it is leaked. Each mutable borrow
+/// starts a new *generation*: the references leaked during previous generations are
+/// invalidated, and accessing them raises `RuntimeError` instead of reading data that
+/// may have been moved or freed.
+#[derive(Debug)]
+pub struct PySharedRefCell<T> {
+    inner: RefCell<T>,
+    py_shared_state: PySharedState,
+}
+
+impl <T> PySharedRefCell<T> {
+    /// Creates a new `PySharedRefCell` containing `value`.
+    pub fn new(value: T) -> PySharedRefCell<T> {
+        PySharedRefCell {
+            inner: RefCell::new(value),
+            py_shared_state: PySharedState::default(),
+        }
+    }
+
+    /// Immutably borrows the value.
+    ///
+    /// Panics if the value is currently mutably borrowed.
+    pub fn borrow<'a>(&'a self, _py: Python<'a>) -> Ref<'a, T> {
+        self.inner.borrow()
+    }
+
+    /// Mutably borrows the value, invalidating the references leaked so far.
+    ///
+    /// Raises `RuntimeError` if the value is currently borrowed, including through
+    /// a leaked reference.
+    pub fn borrow_mut<'a>(&'a self, py: Python<'a>) -> PyResult<RefMut<'a, T>> {
+        if self.py_shared_state.borrow_count.get() > 0 {
+            return Err(PyErr::new::<exc::RuntimeError, _>(py,
+                "Cannot borrow mutably while immutably borrowed by a Python object"));
+        }
+        match self.inner.try_borrow_mut() {
+            Ok(value) => {
+                self.py_shared_state.increment_generation(py);
+                Ok(value)
+            }
+            Err(_) => Err(PyErr::new::<exc::RuntimeError, _>(py, "Cannot borrow mutably while borrowed")),
+        }
+    }
+}
+
+/// A `PySharedRefCell` together with the Python object owning it.
+///
+/// This is the return type of the accessors generated by `py_shared_ref!`.
+pub struct PySharedRef<'a, T: 'a> {
+    py: Python<'a>,
+    owner: &'a PyObject,
+    data: &'a PySharedRefCell<T>,
+}
+
+impl <'a, T> PySharedRef<'a, T> {
+    /// Creates a `PySharedRef` for the data owned by `owner`.
+    ///
+    /// # Safety
+    /// `data` must be stored in the Python object `owner`, e.g. as a `data` member of a `py_class!`.
+    #[doc(hidden)]
+    pub unsafe fn new(py: Python<'a>, owner: &'a PyObject, data: &'a PySharedRefCell<T>) -> Self {
+        PySharedRef { py, owner, data }
+    }
+
+    /// Immutably borrows the value.
+    ///
+    /// Panics if the value is currently mutably borrowed.
+    pub fn borrow(&self) -> Ref<'a, T> {
+        self.data.borrow(self.py)
+    }
+
+    /// Mutably borrows the value, invalidating the references leaked so far.
+    ///
+    /// Raises `RuntimeError` if the value is currently borrowed, including through
+    /// a leaked reference.
+    pub fn borrow_mut(&self) -> PyResult<RefMut<'a, T>> {
+        self.data.borrow_mut(self.py)
+    }
+
+    /// Returns a reference to the value that can be stored in another Python object.
+    ///
+    /// The returned `PyLeaked` keeps the owner alive. It is valid until the value is
+    /// borrowed mutably.
+    /// Raises `RuntimeError` if the value is currently mutably borrowed.
+    ///
+    /// # Safety
+    /// The `&'static T` reference must not be copied out of the `PyLeaked` (or of the values
+    /// derived from it with `PyLeaked::map()`), since it is only valid as long as the
+    /// `PyLeaked` is alive and not invalidated.
+    pub unsafe fn leak_immutable(&self) -> PyResult<PyLeaked<&'static T>> {
+        // Check that the value isn't mutably borrowed; Ref only lives for that check.
+        if self.data.inner.try_borrow().is_err() {
+            return Err(PyErr::new::<exc::RuntimeError, _>(self.py,
+                "Cannot leak immutably while mutably borrowed"));
+        }
+        let state = &self.data.py_shared_state;
+        Ok(PyLeaked {
+            data: &*self.data.inner.as_ptr(),
+            py_shared_state: &*(state as *const PySharedState),
+            generation: state.current_generation(self.py),
+            owner: self.owner.clone_ref(self.py),
+        })
+    }
+}
+
+/// A reference to the content of a `PySharedRefCell`, which can be stored in a `py_class!`.
+///
+/// A `PyLeaked` is obtained with `PySharedRef::leak_immutable()`. It keeps the Python object
+/// owning the `PySharedRefCell` alive. It is invalidated when the content of the
+/// `PySharedRefCell` is borrowed mutably: `try_borrow()` and `try_borrow_mut()` then raise
+/// `RuntimeError`.
+///
+/// The leaked data is dropped without checking whether it was invalidated, so its `Drop`
+/// implementation (if any) must not access the `PySharedRefCell` content.
+pub struct PyLeaked<T> {
+    // data is declared first, so that it's dropped while the owner is still alive
+    data: T,
+    py_shared_state: &'static PySharedState,
+    generation: usize,
+    owner: PyObject,
+}
+
+// The shared state is only accessed with the GIL held.
+unsafe impl <T: Send> Send for PyLeaked<T> {}
+
+impl <T> PyLeaked<T> {
+    /// Gets the Python object owning the data.
+    pub fn owner(&self) -> &PyObject {
+        &self.owner
+    }
+
+    fn validate_generation(&self, py: Python) -> PyResult<()> {
+        if self.py_shared_state.current_generation(py) == self.generation {
+            Ok(())
+        } else {
+            Err(PyErr::new::<exc::RuntimeError, _>(py, "Cannot access to leaked reference after mutation"))
+        }
+    }
+
+    /// Immutably borrows the leaked data.
+    ///
+    /// The data can't be borrowed mutably through the `PySharedRefCell` while the
+    /// `PyLeakedRef` is alive.
+    /// Raises `RuntimeError` if the data was mutably borrowed since it was leaked.
+    pub fn try_borrow<'a>(&'a self, py: Python<'a>) -> PyResult<PyLeakedRef<'a, T>> {
+        self.validate_generation(py)?;
+        self.py_shared_state.increase_borrow_count(py);
+        Ok(PyLeakedRef { py, data: &self.data, py_shared_state: self.py_shared_state })
+    }
+
+    /// Mutably borrows the leaked data (e.g. to advance an iterator).
+    ///
+    /// The data can't be borrowed mutably through the `PySharedRefCell` while the
+    /// `PyLeakedRefMut` is alive.
+    /// Raises `RuntimeError` if the data was mutably borrowed since it was leaked.
+    pub fn try_borrow_mut<'a>(&'a mut self, py: Python<'a>) -> PyResult<PyLeakedRefMut<'a, T>> {
+        self.validate_generation(py)?;
+        self.py_shared_state.increase_borrow_count(py);
+        Ok(PyLeakedRefMut { py, data: &mut self.data, py_shared_state: self.py_shared_state })
+    }
+
+    /// Converts the leaked data, e.g. from a reference to a collection to an iterator over it.
+    ///
+    /// Panics if the data was mutably borrowed since it was leaked.
+    ///
+    /// # Safety
+    /// `f` must not let the leaked references escape, except in the returned value.
+    pub unsafe fn map<U, F>(self, py: Python, f: F) -> PyLeaked<U>
+        where F: FnOnce(T) -> U
+    {
+        self.validate_generation(py).expect("map() over invalidated leaked reference");
+        let data = ptr::read(&self.data);
+        let py_shared_state = self.py_shared_state;
+        let generation = self.generation;
+        let owner = ptr::read(&self.owner);
+        mem::forget(self);
+        PyLeaked { data: f(data), py_shared_state, generation, owner }
+    }
+}
+
+/// An immutable borrow of the data of a `PyLeaked`.
+pub struct PyLeakedRef<'a, T: 'a> {
+    py: Python<'a>,
+    data: &'a T,
+    py_shared_state: &'a PySharedState,
+}
+
+impl <'a, T> Deref for PyLeakedRef<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.data
+    }
+}
+
+impl <'a, T> Drop for PyLeakedRef<'a, T> {
+    fn drop(&mut self) {
+        self.py_shared_state.decrease_borrow_count(self.py);
+    }
+}
+
+/// A mutable borrow of the data of a `PyLeaked`.
+pub struct PyLeakedRefMut<'a, T: 'a> {
+    py: Python<'a>,
+    data: &'a mut T,
+    py_shared_state: &'a PySharedState,
+}
+
+impl <'a, T> Deref for PyLeakedRefMut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.data
+    }
+}
+
+impl <'a, T> DerefMut for PyLeakedRefMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.data
+    }
+}
+
+impl <'a, T> Drop for PyLeakedRefMut<'a, T> {
+    fn drop(&mut self) {
+        self.py_shared_state.decrease_borrow_count(self.py);
+    }
+}
+
+/// Defines an accessor returning a `PySharedRef` for a `PySharedRefCell` data member
+/// of a `py_class!`.
+///
+/// Syntax: `py_shared_ref!(class_name, inner_type, data_member, accessor_name)`
+///
+/// # Example
+/// ```
+/// #[macro_use] extern crate cpython;
+/// use cpython::{PySharedRefCell, PyResult};
+///
+/// py_class!(class Numbers |py| {
+///     data numbers: PySharedRefCell<Vec<u32>>;
+///
+///     def __new__(_cls) -> PyResult<Numbers> {
+///         Numbers::create_instance(py, PySharedRefCell::new(Vec::new()))
+///     }
+///
+///     def push(&self, value: u32) -> PyResult<u32> {
+///         self.shared_numbers(py).borrow_mut()?.push(value);
+///         Ok(value)
+///     }
+///
+///     def __len__(&self) -> PyResult<usize> {
+///         Ok(self.shared_numbers(py).borrow().len())
+///     }
+/// });
+///
+/// py_shared_ref!(Numbers, Vec<u32>, numbers, shared_numbers);
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! py_shared_ref {
+    ($name:ident, $inner_type:ty, $data_member:ident, $accessor:ident) => {
+        impl $name {
+            fn $accessor<'a>(&'a self, py: $crate::Python<'a>) -> $crate::PySharedRef<'a, $inner_type> {
+                let owner = $crate::PythonObject::as_object(self);
+                let data = self.$data_member(py);
+                unsafe { $crate::PySharedRef::new(py, owner, data) }
+            }
+        }
+    };
+}
+
+/// Defines a `py_class!` iterating over data leaked from a `PySharedRefCell`.
+///
+/// Syntax: `py_shared_iterator!(class_name, leaked_type, success_func, success_type)`
+///
+/// * `leaked_type` is the type of the iterator, e.g. `PyLeaked<std::slice::Iter<'static, u32>>`.
+/// * `success_func` converts each item of the iterator:
+///   it is called as `success_func(py, item)` and returns `PyResult<Option<success_type>>`.
+///
+/// The generated class has a `from_inner(py, leaked)` constructor.
+/// `__next__` raises `RuntimeError` if the data was mutated since the iterator was created.
+/// When the iterator is exhausted, the leaked reference is dropped, releasing the owner
+/// even if the Python iterator object is still alive.
+///
+/// # Example
+/// ```
+/// #[macro_use] extern crate cpython;
+/// use std::collections::hash_set::{HashSet, Iter};
+/// use cpython::{PyLeaked, PySharedRefCell, PyResult, Python};
+///
+/// py_class!(class Names |py| {
+///     data names: PySharedRefCell<HashSet<String>>;
+///
+///     def __iter__(&self) -> PyResult<NamesIterator> {
+///         let leaked = unsafe { self.shared_names(py).leak_immutable()? };
+///         NamesIterator::from_inner(py, unsafe { leaked.map(py, |names| names.iter()) })
+///     }
+/// });
+///
+/// py_shared_ref!(Names, HashSet<String>, names, shared_names);
+///
+/// py_shared_iterator!(NamesIterator, PyLeaked<Iter<'static, String>>,
+///                     |_py: Python, name: &String| Ok(Some(name.clone())), String);
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! py_shared_iterator {
+    ($name:ident, $leaked:ty, $success_func:expr, $success_type:ty) => {
+        py_class!(pub class $name |py| {
+            data inner: ::std::cell::RefCell<Option<$leaked>>;
+
+            def __next__(&self) -> $crate::PyResult<Option<$success_type>> {
+                let mut inner = self.inner(py).borrow_mut();
+                if let Some(ref mut leaked) = *inner {
+                    let mut iter = leaked.try_borrow_mut(py)?;
+                    if let Some(item) = iter.next() {
+                        return $success_func(py, item);
+                    }
+                }
+                // Release the data as soon as possible
+                *inner = None;
+                Ok(None)
+            }
+
+            def __iter__(&self) -> $crate::PyResult<Self> {
+                Ok($crate::PyClone::clone_ref(self, py))
+            }
+        });
+
+        impl $name {
+            pub fn from_inner(py: $crate::Python, leaked: $leaked) -> $crate::PyResult<$name> {
+                $name::create_instance(py, ::std::cell::RefCell::new(Some(leaked)))
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use python::Python;
+    use super::{PySharedRef, PySharedRefCell};
+
+    #[test]
+    fn test_borrow() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let owner = py.None();
+        let cell = PySharedRefCell::new(vec![1, 2, 3]);
+        let shared = unsafe { PySharedRef::new(py, &owner, &cell) };
+        shared.borrow_mut().unwrap().push(4);
+        {
+            let _value = shared.borrow();
+            assert!(shared.borrow_mut().is_err());
+        }
+
+        let leaked = unsafe { shared.leak_immutable() }.unwrap();
+        assert_eq!(leaked.try_borrow(py).unwrap().len(), 4);
+        let mut iter = unsafe { leaked.map(py, |v| v.iter()) };
+        {
+            let mut it = iter.try_borrow_mut(py).unwrap();
+            assert_eq!(it.next(), Some(&1));
+            assert!(shared.borrow_mut().is_err());
+        }
+        assert_eq!(iter.try_borrow_mut(py).unwrap().next(), Some(&2));
+
+        // mutation invalidates the leaked references
+        shared.borrow_mut().unwrap().clear();
+        assert!(iter.try_borrow_mut(py).is_err());
+        drop(iter);
+
+        let _value = shared.borrow_mut().unwrap();
+        assert!(unsafe { shared.leak_immutable() }.is_err());
+    }
+}
@@ -0,0 +1,454 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Utilities for testing extension modules.
+//!
+//! The `py_run!`, `py_assert!` and `py_expect_exception!` macros run Python code
+//! with some Rust values as local variables, and panic with the Python traceback if the
+//! code fails:
+//!
+//! ```
+//! #[macro_use] extern crate cpython;
+//! use cpython::{Python, PyList, ToPyObject};
+//!
+//! fn main() {
+//!     let gil = Python::acquire_gil();
+//!     let py = gil.python();
+//!     let list = vec![1, 2, 3].to_py_object(py);
+//!     let total = 6;
+//!     py_run!(py, list, "list.append(4)");
+//!     py_assert!(py, list total, "sum(list[:3]) == total");
+//!     py_expect_exception!(py, list, "list[10]", IndexError);
+//! }
+//! ```
+//!
+//! The `py_test!` macro declares a test running with the GIL held, and restores
+//! `sys.modules` and the globals of `__main__` when it ends:
+//!
+//! ```
+//! #[macro_use] extern crate cpython;
+//!
+//! py_test!(fn test_import(py) {
+//!     py.run("import json", None, None).unwrap();
+//!     py_assert!(py, , "json.loads('[1]') == [1]");
+//! });
+//!
+//! py_test!(fn test_globals(py, globals) {
+//!     py.run("x = 42", Some(&globals), None).unwrap();
+//! });
+//! # fn main() {}
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use err::{PyErr, PyResult};
+use objectprotocol::ObjectProtocol;
+use objects::{PyDict, PyObject, PyType};
+use python::Python;
+use pythonrun::GILGuard;
+use inline::dedent;
+
+/// The objects alive at some point, as observed by the garbage collector.
+struct Snapshot {
+    /// `sys.gettotalrefcount()`, only available on debug builds of Python.
+    total_refcount: Option<isize>,
+    /// The number of objects tracked by the garbage collector, by type.
+    objects: HashMap<String, isize>,
+}
+
+impl Snapshot {
+    fn take(py: Python) -> PyResult<Snapshot> {
+        let gc = py.import("gc")?;
+        gc.call(py, "collect", ::NoArgs, None)?;
+        let sys = py.import("sys")?;
+        let total_refcount = match sys.get(py, "gettotalrefcount") {
+            Ok(f) => Some(f.call(py, ::NoArgs, None)?.extract::<isize>(py)?),
+            Err(_) => None,
+        };
+        let mut objects = HashMap::new();
+        for obj in gc.call(py, "get_objects", ::NoArgs, None)?.iter(py)? {
+            let name: String = obj?.get_type(py).name(py).into_owned();
+            *objects.entry(name).or_insert(0) += 1;
+        }
+        Ok(Snapshot { total_refcount, objects })
+    }
+
+    /// The number of objects tracked by the garbage collector.
+    fn object_count(&self) -> isize {
+        self.objects.values().sum()
+    }
+
+    /// Describes the objects added since `before`, or returns `None` if there are none.
+    ///
+    /// Fewer objects is not reported: the garbage collector stops tracking some objects
+    /// (e.g. tuples of numbers) when it encounters them during a collection.
+    fn diff(&self, before: &Snapshot) -> Option<String> {
+        let mut changes = BTreeMap::new();
+        for (name, &count) in &self.objects {
+            let delta = count - before.objects.get(name).cloned().unwrap_or(0);
+            if delta > 0 {
+                changes.insert(&name[..], delta);
+            }
+        }
+        let refcount_delta = match (self.total_refcount, before.total_refcount) {
+            (Some(after), Some(before)) => after - before,
+            _ => 0,
+        };
+        if changes.is_empty() && refcount_delta == 0 {
+            return None;
+        }
+        let mut report = String::new();
+        if refcount_delta != 0 {
+            report.push_str(&format!("\n  total refcount: {:+}", refcount_delta));
+        }
+        for (name, delta) in changes {
+            report.push_str(&format!("\n  {}: {:+}", name, delta));
+        }
+        Some(report)
+    }
+}
+
+/// Panics if calling `f` leaks Python objects.
+///
+/// `f` is called twice: the first call warms up the caches (e.g. imported modules or
+/// interned strings), and the objects alive before and after the second call are compared.
+/// The panic message lists the number of leaked objects by type.
+///
+/// Only the objects tracked by the garbage collector (e.g. containers and instances of
+/// classes) are counted, except on debug builds of Python where the total reference count
+/// is compared too.
+///
+/// ```
+/// use cpython::PyList;
+/// use cpython::testing::assert_no_leaks;
+///
+/// assert_no_leaks(|py| {
+///     let list = PyList::new(py, &[]);
+///     assert_eq!(list.len(py), 0);
+/// });
+/// ```
+pub fn assert_no_leaks<F>(mut f: F) where F: FnMut(Python) {
+    let gil = GILGuard::acquire();
+    let py = gil.python();
+    f(py);
+    let before = Snapshot::take(py).expect("failed to inspect the Python objects");
+    f(py);
+    let after = Snapshot::take(py).expect("failed to inspect the Python objects");
+    if let Some(report) = after.diff(&before) {
+        panic!("Python objects leaked:{}", report);
+    }
+}
+
+/// Panics if the objects alive grow on each of `n` calls to `f`, like the `-R` option of
+/// CPython's regression tests.
+///
+/// `f` is called once to warm up the caches, then `n` times. The panic message lists the
+/// growth of the number of objects (and of the total reference count, on debug builds of
+/// Python) on each call, and the objects leaked by type.
+///
+/// Unlike `assert_no_leaks`, this tolerates objects allocated by some calls only
+/// (e.g. caches filled lazily, or free lists), but `n` must be at least 2.
+///
+/// ```
+/// use cpython::PyDict;
+/// use cpython::testing::repeat_and_check_refs;
+///
+/// repeat_and_check_refs(5, |py| {
+///     let d = PyDict::new(py);
+///     d.set_item(py, "key", "value").unwrap();
+/// });
+/// ```
+pub fn repeat_and_check_refs<F>(n: usize, mut f: F) where F: FnMut(Python) {
+    assert!(n >= 2, "repeat_and_check_refs() needs at least 2 runs");
+    let gil = GILGuard::acquire();
+    let py = gil.python();
+    f(py);
+    let first = Snapshot::take(py).expect("failed to inspect the Python objects");
+    let mut previous = Snapshot::take(py).expect("failed to inspect the Python objects");
+    let mut object_deltas = Vec::with_capacity(n);
+    let mut refcount_deltas = Vec::with_capacity(n);
+    for _ in 0..n {
+        f(py);
+        let snapshot = Snapshot::take(py).expect("failed to inspect the Python objects");
+        object_deltas.push(snapshot.object_count() - previous.object_count());
+        if let (Some(after), Some(before)) = (snapshot.total_refcount, previous.total_refcount) {
+            refcount_deltas.push(after - before);
+        }
+        previous = snapshot;
+    }
+    let leaking = |deltas: &[isize]| !deltas.is_empty() && deltas.iter().all(|&d| d > 0);
+    if leaking(&object_deltas) || leaking(&refcount_deltas) {
+        let mut report = format!("objects: {:?}", object_deltas);
+        if !refcount_deltas.is_empty() {
+            report.push_str(&format!(", total refcount: {:?}", refcount_deltas));
+        }
+        panic!("Python objects leaked on each of {} runs ({}):{}",
+               n, report, previous.diff(&first).unwrap_or_default());
+    }
+}
+
+/// The state restored at the end of a `py_test!`: `sys.modules` and the globals of `__main__`.
+struct Isolation {
+    saved: Vec<(PyDict, PyDict)>,
+}
+
+impl Isolation {
+    fn save(py: Python) -> PyResult<Isolation> {
+        let modules = py.import("sys")?.get(py, "modules")?.cast_into::<PyDict>(py)?;
+        let main = py.import("__main__")?.dict(py);
+        let modules_copy = modules.copy(py)?;
+        let main_copy = main.copy(py)?;
+        Ok(Isolation { saved: vec![(modules, modules_copy), (main, main_copy)] })
+    }
+
+    fn restore(&self, py: Python) -> PyResult<()> {
+        for saved in &self.saved {
+            let (dict, copy) = (&saved.0, &saved.1);
+            for (key, _) in dict.items(py) {
+                if !copy.contains(py, &key)? {
+                    dict.del_item(py, key)?;
+                }
+            }
+            for (key, value) in copy.items(py) {
+                dict.set_item(py, key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Isolation {
+    fn drop(&mut self) {
+        // Also called when the test panics.
+        let gil = GILGuard::acquire();
+        if let Err(err) = self.restore(gil.python()) {
+            err.print(gil.python());
+        }
+    }
+}
+
+/// Runs the body of a `py_test!`.
+#[doc(hidden)]
+pub fn run_test<F>(f: F) where F: FnOnce(Python) {
+    let gil = GILGuard::acquire();
+    let py = gil.python();
+    let _isolation = Isolation::save(py).expect("failed to save the state of the interpreter");
+    f(py);
+}
+
+/// Formats `err` like Python does for uncaught exceptions, including the traceback.
+fn format_exception(py: Python, mut err: PyErr) -> String {
+    let value = err.instance(py);
+    let traceback = err.ptraceback.take().unwrap_or_else(|| py.None());
+    let lines = py.import("traceback")
+        .and_then(|m| m.call(py, "format_exception", (err.get_type(py), value, traceback), None))
+        .and_then(|lines| lines.extract::<Vec<String>>(py));
+    match lines {
+        Ok(lines) => lines.concat(),
+        Err(_) => format!("{:?}", err),
+    }
+}
+
+/// Runs `code` for `py_run!`, panicking if it raises an exception.
+#[doc(hidden)]
+pub fn run_or_panic(py: Python, code: &str, locals: &PyDict) {
+    let result = dedent(py, code).and_then(|code| py.run(&code, None, Some(locals)));
+    if let Err(err) = result {
+        panic!("Python code failed: {}\n{}", code.trim(), format_exception(py, err));
+    }
+}
+
+/// Runs `code` for `py_expect_exception!`, panicking unless it raises an instance of `ty`.
+#[doc(hidden)]
+pub fn expect_exception(py: Python, code: &str, locals: &PyDict, ty: PyType) -> PyErr {
+    match dedent(py, code).and_then(|code| py.run(&code, None, Some(locals))) {
+        Ok(()) => panic!("Python code did not raise {}: {}", ty.name(py), code.trim()),
+        Err(err) => {
+            if !err.matches(py, &ty) {
+                panic!("Python code raised another exception than {}: {}\n{}",
+                       ty.name(py), code.trim(), format_exception(py, err));
+            }
+            err
+        }
+    }
+}
+
+/// Runs Python code with some Rust values as local variables, and panics with the
+/// Python traceback if the code raises an exception.
+///
+/// Syntax: `py_run!(py, var1 var2 ..., code)`: the variables must implement `ToPyObject`,
+/// and are accessible to the code under the same names.
+/// The code can be indented to match the surrounding Rust code.
+///
+/// See the [`testing` module](testing/index.html) for an example.
+#[macro_export]
+macro_rules! py_run {
+    ($py:expr, $($val:ident)*, $code:expr) => {{
+        let py = $py;
+        let d = $crate::PyDict::new(py);
+        $( d.set_item(py, stringify!($val), &$val).unwrap(); )*
+        $crate::testing::run_or_panic(py, $code, &d);
+    }};
+}
+
+/// Evaluates a Python assertion with some Rust values as local variables, and panics
+/// if it fails.
+///
+/// Syntax: `py_assert!(py, var1 var2 ..., assertion)`, where `assertion` is a
+/// Python expression. See `py_run!`.
+#[macro_export]
+macro_rules! py_assert {
+    ($py:expr, $($val:ident)*, $assertion:expr) => {
+        py_run!($py, $($val)*, concat!("assert ", $assertion, ", ", stringify!($assertion)))
+    };
+}
+
+/// Runs Python code with some Rust values as local variables, and panics unless
+/// it raises the given exception type (one of the types in `cpython::exc`).
+///
+/// Syntax: `py_expect_exception!(py, var1 var2 ..., code, ExceptionType)`.
+/// Evaluates to the `PyErr`. See `py_run!`.
+#[macro_export]
+macro_rules! py_expect_exception {
+    ($py:expr, $($val:ident)*, $code:expr, $err:ident) => {{
+        let py = $py;
+        let d = $crate::PyDict::new(py);
+        $( d.set_item(py, stringify!($val), &$val).unwrap(); )*
+        $crate::testing::expect_exception(py, $code, &d, py.get_type::<$crate::exc::$err>())
+    }};
+}
+
+/// Declares a test that runs with the GIL held.
+///
+/// Syntax: `py_test!(fn name(py) { ... })`, or `py_test!(fn name(py, globals) { ... })`
+/// to get a new `PyDict` for the globals of the Python code run by the test.
+/// Attributes (e.g. `#[should_panic]`) can be put before `fn`.
+///
+/// The interpreter is initialized on the first test. When the test ends (even with a panic),
+/// the modules it imported are removed from `sys.modules` and the globals of `__main__` are
+/// restored, so that the tests don't depend on each other.
+///
+/// See the [`testing` module](testing/index.html) for an example.
+#[macro_export]
+macro_rules! py_test {
+    ($(#[$attr:meta])* fn $name:ident ($py:ident) $body:block) => {
+        #[test]
+        $(#[$attr])*
+        fn $name() {
+            $crate::testing::run_test(|$py: $crate::Python| $body);
+        }
+    };
+    ($(#[$attr:meta])* fn $name:ident ($py:ident, $globals:ident) $body:block) => {
+        #[test]
+        $(#[$attr])*
+        fn $name() {
+            $crate::testing::run_test(|$py: $crate::Python| {
+                let $globals = $crate::PyDict::new($py);
+                $body
+            });
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use std::{mem, panic};
+    use python::{Python, PythonObject};
+    use objects::{PyDict, PyList};
+    use objectprotocol::ObjectProtocol;
+    use super::{assert_no_leaks, repeat_and_check_refs, run_test};
+
+    #[test]
+    fn test_no_leaks() {
+        assert_no_leaks(|py| {
+            let d = PyDict::new(py);
+            d.set_item(py, "list", PyList::new(py, &[])).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_leak() {
+        let result = panic::catch_unwind(|| {
+            assert_no_leaks(|py| mem::forget(PyList::new(py, &[])));
+        });
+        let message = result.unwrap_err();
+        let message = message.downcast_ref::<String>().unwrap();
+        assert!(message.contains("list: +1"), "{}", message);
+    }
+
+    #[test]
+    fn test_repeat_and_check_refs() {
+        let mut calls = 0;
+        repeat_and_check_refs(3, |py| {
+            if calls == 1 {
+                // A single allocation isn't reported.
+                mem::forget(PyList::new(py, &[]));
+            }
+            calls += 1;
+        });
+        assert_eq!(calls, 4);
+
+        let result = panic::catch_unwind(|| {
+            repeat_and_check_refs(3, |py| mem::forget(PyList::new(py, &[])));
+        });
+        let message = result.unwrap_err();
+        let message = message.downcast_ref::<String>().unwrap();
+        assert!(message.contains("on each of 3 runs"), "{}", message);
+        assert!(message.contains("list: +3"), "{}", message);
+    }
+
+    #[test]
+    fn test_py_run() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let list = PyList::new(py, &[]);
+        let value = 42;
+        py_run!(py, list value, "
+            list.append(value)
+            list.append(value + 1)
+        ");
+        py_assert!(py, list, "list == [42, 43]");
+        py_expect_exception!(py, list, "list.missing", AttributeError);
+
+        let result = panic::catch_unwind(|| {
+            let gil = Python::acquire_gil();
+            let two = 2;
+            py_assert!(gil.python(), two, "two + 1 == 2");
+        });
+        let message = result.unwrap_err();
+        let message = message.downcast_ref::<String>().unwrap();
+        assert!(message.contains("AssertionError: two + 1 == 2"), "{}", message);
+    }
+
+    py_test!(fn test_py_test(py, globals) {
+        py.run("x = 1", Some(&globals), None).unwrap();
+        py_assert!(py, globals, "globals['x'] == 1");
+    });
+
+    #[test]
+    fn test_isolation() {
+        run_test(|py| {
+            py.run("import colorsys\nisolation_global = 1", None, None).unwrap();
+        });
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let modules = py.import("sys").unwrap().get(py, "modules").unwrap();
+        assert!(!modules.call_method(py, "__contains__", ("colorsys",), None).unwrap().is_true(py).unwrap());
+        let main = py.import("__main__").unwrap();
+        assert!(!main.as_object().hasattr(py, "isolation_global").unwrap());
+    }
+}
@@ -0,0 +1,313 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Forwarding of [`tracing`](https://docs.rs/tracing) events to the Python `logging` module.
+//!
+//! This module requires the `tracing` feature, whose dependencies need a newer Rust compiler
+//! than the rest of this crate (see their documentation for the minimum supported version).
+//!
+//! `PythonLoggingLayer` is a [`tracing-subscriber`](https://docs.rs/tracing-subscriber) layer
+//! which turns every event into a Python `LogRecord`, so that the events of a Rust extension
+//! end up in the same handlers as the logs of the Python application. From there, they can
+//! also reach any observability backend (e.g. OpenTelemetry) that is hooked into `logging`.
+//!
+//! ```ignore
+//! use tracing_subscriber::prelude::*;
+//!
+//! tracing_subscriber::registry().with(cpython::tracing::PythonLoggingLayer::new()).init();
+//! ```
+//!
+//! The logger name is the event's target, with `::` replaced by `.`.
+//! The message lists the spans the event happened in, like the `fmt` subscriber does:
+//! `request{id=3}:parse: unexpected token kind=Comma`.
+//! The record also gets the following attributes:
+//!
+//!  * `rust_spans`: the list of the names of the enclosing spans, outermost first.
+//!  * `rust_fields`: a dict with the fields of the event (except `message`), as strings.
+
+extern crate tracing_core;
+extern crate tracing_subscriber;
+
+use std::fmt::{self, Write};
+use self::tracing_core::{Event, Level, Subscriber};
+use self::tracing_core::field::{Field, Visit};
+use self::tracing_core::span::{Attributes, Id, Record};
+use self::tracing_subscriber::layer::{Context, Layer};
+use self::tracing_subscriber::registry::LookupSpan;
+use err::PyResult;
+use objectprotocol::ObjectProtocol;
+use objects::PyDict;
+use python::Python;
+
+/// A `tracing-subscriber` layer forwarding events to the Python `logging` module.
+///
+/// The GIL is acquired only when an event is emitted.
+pub struct PythonLoggingLayer {
+    _private: (),
+}
+
+impl PythonLoggingLayer {
+    pub fn new() -> PythonLoggingLayer {
+        PythonLoggingLayer { _private: () }
+    }
+}
+
+impl Default for PythonLoggingLayer {
+    fn default() -> PythonLoggingLayer {
+        PythonLoggingLayer::new()
+    }
+}
+
+/// Gets the Python logging level corresponding to a tracing level.
+pub fn python_level(level: &Level) -> i32 {
+    match *level {
+        Level::ERROR => 40,
+        Level::WARN => 30,
+        Level::INFO => 20,
+        Level::DEBUG => 10,
+        Level::TRACE => 5,
+    }
+}
+
+/// The formatted fields of a span, stored in the span's extensions.
+struct SpanFields(String);
+
+/// Formats fields as `name=value` pairs separated by spaces.
+struct FieldFormatter<'a>(&'a mut String);
+
+impl<'a> Visit for FieldFormatter<'a> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        let _ = write!(self.0, "{}={:?}", field.name(), value);
+    }
+}
+
+/// Collects the fields of an event.
+#[derive(Default)]
+struct EventFields {
+    message: String,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Visit for EventFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.push((field.name(), value.to_string()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields.push((field.name(), format!("{:?}", value)));
+        }
+    }
+}
+
+/// An event, formatted while the span data is available.
+struct FormattedEvent {
+    target: String,
+    level: i32,
+    file: Option<&'static str>,
+    line: Option<u32>,
+    message: String,
+    spans: Vec<&'static str>,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl FormattedEvent {
+    fn emit(self, py: Python) -> PyResult<()> {
+        let name = self.target.replace("::", ".");
+        let logger = py.import("logging")?.call(py, "getLogger", (&name,), None)?;
+        if !logger.call_method(py, "isEnabledFor", (self.level,), None)?.is_true(py)? {
+            return Ok(());
+        }
+        let record = logger.call_method(py, "makeRecord",
+            (&name, self.level, self.file.unwrap_or("<unknown>"), self.line.unwrap_or(0),
+             self.message, ::NoArgs, py.None()), None)?;
+        let fields = PyDict::new(py);
+        for (k, v) in self.fields {
+            fields.set_item(py, k, v)?;
+        }
+        record.setattr(py, "rust_spans", self.spans)?;
+        record.setattr(py, "rust_fields", fields)?;
+        logger.call_method(py, "handle", (record,), None)?;
+        Ok(())
+    }
+}
+
+impl<S> Layer<S> for PythonLoggingLayer
+    where S: Subscriber + for<'a> LookupSpan<'a>
+{
+    fn on_new_span(&self, attrs: &Attributes, id: &Id, ctx: Context<S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut fields = String::new();
+            attrs.record(&mut FieldFormatter(&mut fields));
+            span.extensions_mut().insert(SpanFields(fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record, ctx: Context<S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut extensions = span.extensions_mut();
+            if let Some(fields) = extensions.get_mut::<SpanFields>() {
+                values.record(&mut FieldFormatter(&mut fields.0));
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event, ctx: Context<S>) {
+        let metadata = event.metadata();
+        let mut event_fields = EventFields::default();
+        event.record(&mut event_fields);
+
+        let mut message = String::new();
+        let mut spans = Vec::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                spans.push(span.name());
+                message.push_str(span.name());
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    if !fields.0.is_empty() {
+                        let _ = write!(message, "{{{}}}", fields.0);
+                    }
+                }
+                message.push(':');
+            }
+        }
+        if !message.is_empty() {
+            message.push(' ');
+        }
+        message.push_str(&event_fields.message);
+        for &(k, ref v) in &event_fields.fields {
+            let _ = write!(message, " {}={}", k, v);
+        }
+
+        let formatted = FormattedEvent {
+            target: metadata.target().to_string(),
+            level: python_level(metadata.level()),
+            file: metadata.file(),
+            line: metadata.line(),
+            message,
+            spans,
+            fields: event_fields.fields,
+        };
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        if let Err(e) = formatted.emit(py) {
+            // There's no caller to report the error to
+            e.print(py);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::tracing_core::{dispatcher, metadata, Callsite, Dispatch, Event, Interest, Kind, Level, Metadata};
+    use super::tracing_core::field::Value;
+    use super::tracing_core::span::Attributes;
+    use super::tracing_subscriber::prelude::*;
+    use super::tracing_subscriber::registry;
+    use objects::PyDict;
+    use python::Python;
+    use super::PythonLoggingLayer;
+
+    // Only `tracing-core` is a dependency, so the callsites that the `tracing` macros
+    // would create are declared by hand.
+    struct TestCallsite(&'static Metadata<'static>);
+
+    impl Callsite for TestCallsite {
+        fn set_interest(&self, _interest: Interest) {}
+
+        fn metadata(&self) -> &Metadata {
+            self.0
+        }
+    }
+
+    static SPAN_CALLSITE: TestCallsite = TestCallsite(&SPAN);
+    static SPAN: Metadata<'static> = metadata! {
+        name: "request", target: "cpython_test::tracing", level: Level::INFO,
+        fields: &["id"], callsite: &SPAN_CALLSITE, kind: Kind::SPAN
+    };
+    static WARN_CALLSITE: TestCallsite = TestCallsite(&WARN);
+    static WARN: Metadata<'static> = metadata! {
+        name: "warn", target: "cpython_test::tracing", level: Level::WARN,
+        fields: &["message", "kind"], callsite: &WARN_CALLSITE, kind: Kind::EVENT
+    };
+    static DEBUG_CALLSITE: TestCallsite = TestCallsite(&DEBUG);
+    static DEBUG: Metadata<'static> = metadata! {
+        name: "debug", target: "cpython_test::tracing", level: Level::DEBUG,
+        fields: &["message"], callsite: &DEBUG_CALLSITE, kind: Kind::EVENT
+    };
+
+    #[test]
+    fn test_forward_event() {
+        {
+            let gil = Python::acquire_gil();
+            let py = gil.python();
+            py.run("import logging\n\
+                    class H(logging.Handler):\n    \
+                        records = []\n    \
+                        def emit(self, record):\n        \
+                            self.records.append(record)\n\
+                    logger = logging.getLogger('cpython_test.tracing')\n\
+                    logger.setLevel(logging.INFO)\n\
+                    logger.addHandler(H())\n\
+                    import __main__\n\
+                    __main__.TracingHandler = H\n", None, None).unwrap();
+        }
+
+        let dispatch = Dispatch::new(registry().with(PythonLoggingLayer::new()));
+        dispatcher::with_default(&dispatch, || {
+            // info_span!("request", id = 3).enter()
+            let fields = SPAN.fields();
+            let id = dispatch.new_span(&Attributes::new(&SPAN,
+                &fields.value_set(&[(&fields.field("id").unwrap(), Some(&3i64 as &dyn Value))])));
+            dispatch.enter(&id);
+            // warn!(kind = "comma", "unexpected token")
+            let fields = WARN.fields();
+            Event::dispatch(&WARN, &fields.value_set(&[
+                (&fields.field("message").unwrap(), Some(&"unexpected token" as &dyn Value)),
+                (&fields.field("kind").unwrap(), Some(&"comma" as &dyn Value)),
+            ]));
+            // debug!("ignored")
+            let fields = DEBUG.fields();
+            Event::dispatch(&DEBUG, &fields.value_set(&[
+                (&fields.field("message").unwrap(), Some(&"ignored" as &dyn Value)),
+            ]));
+            dispatch.exit(&id);
+            dispatch.try_close(id);
+        });
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let d = PyDict::new(py);
+        d.set_item(py, "H", py.eval("TracingHandler", None, None).unwrap()).unwrap();
+        let r: (usize, String, String, Vec<String>, String) = py.eval(
+            "(len(H.records), H.records[0].getMessage(), H.records[0].levelname, \
+              H.records[0].rust_spans, H.records[0].rust_fields['kind'])",
+            None, Some(&d)).unwrap().extract(py).unwrap();
+        assert_eq!(r, (1, "request{id=3}: unexpected token kind=comma".to_string(),
+                       "WARNING".to_string(), vec!["request".to_string()], "comma".to_string()));
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Support for the `extract_union!` macro.
+
+use err::PyErr;
+use exc;
+use objectprotocol::ObjectProtocol;
+use objects::PyObject;
+use python::Python;
+
+/// Collects the errors of the alternatives tried by an `extract_union!` enum.
+#[doc(hidden)]
+#[derive(Default)]
+pub struct UnionErrors {
+    attempts: Vec<String>,
+}
+
+impl UnionErrors {
+    pub fn new() -> UnionErrors {
+        UnionErrors { attempts: Vec::new() }
+    }
+
+    /// Records that the extraction of `variant` failed with `err`.
+    pub fn push(&mut self, py: Python, variant: &str, mut err: PyErr) {
+        let message = err.instance(py).str(py).map(|s| s.to_string_lossy(py).into_owned()).unwrap_or_default();
+        self.attempts.push(format!("{}: {}", variant, message));
+    }
+
+    /// Records that `variant`, which only matches `None`, was tried.
+    pub fn push_none(&mut self, variant: &str) {
+        self.attempts.push(format!("{}: expected None", variant));
+    }
+
+    /// Creates the `TypeError` listing the alternatives that were tried.
+    pub fn into_err(self, py: Python, name: &str, obj: &PyObject) -> PyErr {
+        PyErr::new::<exc::TypeError, _>(py, format!("cannot extract {} from '{}' object ({})",
+            name, obj.get_type(py).name(py), self.attempts.join("; ")))
+    }
+}
+
+/// Defines an enum which is extracted from the first of its variants that matches the
+/// Python object, like the Python type annotation `Union[...]`.
+///
+/// Syntax:
+///
+/// ```ignore
+/// extract_union! {
+///     pub enum Name {
+///         Variant(Type),
+///         ...
+///         NoneVariant,
+///     }
+/// }
+/// ```
+///
+/// Each variant either holds a single value, extracted with `FromPyObject`,
+/// or is a unit variant, that matches `None`.
+/// The variants are tried in order, so e.g. a `bool` variant has to come before an `i64`
+/// variant (since Python `bool` is a subtype of `int`).
+///
+/// If no variant matches, the extraction fails with a `TypeError` listing the
+/// error for each variant.
+///
+/// ```
+/// #[macro_use] extern crate cpython;
+/// use cpython::Python;
+///
+/// extract_union! {
+///     #[derive(Debug, PartialEq)]
+///     pub enum Key {
+///         /// a key given by name
+///         Name(String),
+///         Index(i64),
+///         Missing,
+///     }
+/// }
+///
+/// fn main() {
+///     let gil = Python::acquire_gil();
+///     let py = gil.python();
+///     let key: Key = py.eval("'id'", None, None).unwrap().extract(py).unwrap();
+///     assert_eq!(key, Key::Name("id".to_owned()));
+///     assert_eq!(py.None().extract::<Key>(py).unwrap(), Key::Missing);
+///     assert!(py.eval("1j", None, None).unwrap().extract::<Key>(py).is_err());
+/// }
+/// ```
+#[macro_export]
+macro_rules! extract_union {
+    ($(#[$attr:meta])* enum $name:ident { $($body:tt)* }) => {
+        extract_union_impl! { @parse [py obj errors] [$(#[$attr])* enum] $name [] [] $($body)* }
+    };
+    ($(#[$attr:meta])* pub enum $name:ident { $($body:tt)* }) => {
+        extract_union_impl! { @parse [py obj errors] [$(#[$attr])* pub enum] $name [] [] $($body)* }
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! extract_union_impl {
+    // `[py obj errors]` are the names of the variables of `extract()`, which are passed along
+    // so that the code of each variant can use them (because of macro hygiene).
+    // Each variant adds its declaration to the first list, and the code trying it
+    // to the second list. A missing trailing comma is added first.
+    (@parse $vars:tt $decl:tt $name:ident $variants:tt $tries:tt
+        $(#[$vattr:meta])* $variant:ident $( ( $ty:ty ) )*
+    ) => {
+        extract_union_impl! { @parse $vars $decl $name $variants $tries $(#[$vattr])* $variant $( ( $ty ) )*, }
+    };
+    (@parse [ $py:ident $obj:ident $errors:ident ] $decl:tt $name:ident [ $($variants:tt)* ] [ $($tries:tt)* ]
+        $(#[$vattr:meta])* $variant:ident ( $ty:ty ), $($rest:tt)*
+    ) => {
+        extract_union_impl! { @parse [ $py $obj $errors ] $decl $name
+            [ $($variants)* $(#[$vattr])* $variant($ty), ]
+            [ $($tries)* {
+                match <$ty as $crate::FromPyObject>::extract($py, $obj) {
+                    Ok(value) => return Ok($name::$variant(value)),
+                    Err(e) => $errors.push($py, concat!(stringify!($variant), "(", stringify!($ty), ")"), e),
+                }
+            } ]
+            $($rest)*
+        }
+    };
+    (@parse [ $py:ident $obj:ident $errors:ident ] $decl:tt $name:ident [ $($variants:tt)* ] [ $($tries:tt)* ]
+        $(#[$vattr:meta])* $variant:ident, $($rest:tt)*
+    ) => {
+        extract_union_impl! { @parse [ $py $obj $errors ] $decl $name
+            [ $($variants)* $(#[$vattr])* $variant, ]
+            [ $($tries)* {
+                if *$obj == $py.None() {
+                    return Ok($name::$variant);
+                }
+                $errors.push_none(stringify!($variant));
+            } ]
+            $($rest)*
+        }
+    };
+    (@parse [ $py:ident $obj:ident $errors:ident ] [ $($decl:tt)* ] $name:ident [ $($variants:tt)* ] [ $($tries:tt)* ]) => {
+        $($decl)* $name {
+            $($variants)*
+        }
+
+        impl <'s> $crate::FromPyObject<'s> for $name {
+            fn extract($py: $crate::Python, $obj: &'s $crate::PyObject) -> $crate::PyResult<$name> {
+                let mut $errors = $crate::_detail::UnionErrors::new();
+                $($tries)*
+                Err($errors.into_err($py, stringify!($name), $obj))
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use python::Python;
+    use exc;
+
+    extract_union! {
+        #[derive(Debug, PartialEq)]
+        enum Value {
+            Flag(bool),
+            Int(i64),
+            Text(String),
+            List(Vec<Value>),
+            Nothing
+        }
+    }
+
+    #[test]
+    fn test_extract_union() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let extract = |code| py.eval(code, None, None).unwrap().extract::<Value>(py);
+        assert_eq!(extract("True").unwrap(), Value::Flag(true));
+        assert_eq!(extract("42").unwrap(), Value::Int(42));
+        assert_eq!(extract("'abc'").unwrap(), Value::Text("abc".to_owned()));
+        assert_eq!(extract("None").unwrap(), Value::Nothing);
+        assert_eq!(extract("[1, None]").unwrap(), Value::List(vec![Value::Int(1), Value::Nothing]));
+
+        let mut err = extract("1j").unwrap_err();
+        assert!(err.matches(py, py.get_type::<exc::TypeError>()));
+        let message = err.instance(py).to_string();
+        assert!(message.starts_with("cannot extract Value from 'complex' object (Flag(bool): "), "{}", message);
+        assert!(message.contains("; Int(i64): "), "{}", message);
+        assert!(message.ends_with("; Nothing: expected None)"), "{}", message);
+    }
+}
@@ -0,0 +1,642 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Read-only Python views over Rust collections.
+//!
+//! Converting a large Rust collection to a Python `list` creates a Python object for each
+//! item up front. The views defined in this module instead keep the Rust collection, and only
+//! convert the items that Python code actually accesses.
+//!
+//! ```
+//! use cpython::{Python, PyDict};
+//! use cpython::views::VecView;
+//!
+//! let gil = Python::acquire_gil();
+//! let py = gil.python();
+//! let view = VecView::new(py, (0..1000000u64).collect()).unwrap();
+//! let locals = PyDict::new(py);
+//! locals.set_item(py, "view", view).unwrap();
+//! py.run("assert len(view) == 1000000 and view[-1] == 999999 and list(view[:3]) == [0, 1, 2]",
+//!        None, Some(&locals)).unwrap();
+//! ```
+//!
+//! `MapView` similarly exposes a `HashMap` or a `BTreeMap` as a read-only Python mapping,
+//! and `BytesView` exposes a Rust byte buffer with the buffer protocol.
+
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+use std::{mem, ptr};
+use std::sync::Arc;
+use libc::{c_char, c_int};
+use ffi;
+use conversion::{FromPyObject, ToPyObject};
+use err::{self, PyErr, PyResult};
+use exc;
+use function::{handle_callback, AbortOnDrop};
+use objects::{PyObject, PyType};
+use objectprotocol::ObjectProtocol;
+use python::{Python, PythonObject, PythonObjectWithCheckedDowncast, PythonObjectDowncastError, PyClone};
+use py_class::{self, BaseObject, CompareOp};
+use py_class::slots::{self, UnitCallbackConverter};
+
+/// Type-erased access to the items of a `Vec`.
+#[doc(hidden)]
+pub trait SequenceData: Send + Sync {
+    fn get_item(&self, py: Python, index: usize) -> PyObject;
+}
+
+impl <T> SequenceData for Vec<T> where T: ToPyObject + Send + Sync {
+    fn get_item(&self, py: Python, index: usize) -> PyObject {
+        self[index].to_py_object(py).into_object()
+    }
+}
+
+py_class!(pub class VecView |py| {
+    data items: Arc<SequenceData>;
+    // The view contains the items at positions `start + i * step` for `i` in `0..len`
+    data start: isize;
+    data step: isize;
+    data length: usize;
+
+    def __len__(&self) -> PyResult<usize> {
+        Ok(*self.length(py))
+    }
+
+    def __getitem__(&self, key: PyObject) -> PyResult<PyObject> {
+        if unsafe { ffi::PySlice_Check(key.as_ptr()) } != 0 {
+            return self.slice(py, &key).map(|view| view.into_object());
+        }
+        let index = key.extract::<isize>(py)?;
+        let len = *self.length(py) as isize;
+        let index = if index < 0 { index + len } else { index };
+        if index < 0 || index >= len {
+            return Err(PyErr::new::<exc::IndexError, _>(py, "index out of range"));
+        }
+        Ok(self.item(py, index as usize))
+    }
+
+    def __iter__(&self) -> PyResult<VecViewIterator> {
+        VecViewIterator::create_instance(py, self.clone_ref(py), Cell::new(0))
+    }
+
+    def __contains__(&self, item: PyObject) -> PyResult<bool> {
+        for i in 0..*self.length(py) {
+            if self.item(py, i).rich_compare(py, &item, CompareOp::Eq)?.is_true(py)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    def __repr__(&self) -> PyResult<String> {
+        Ok(format!("<VecView of {} items>", self.length(py)))
+    }
+});
+
+impl VecView {
+    /// Creates a view over `items`.
+    pub fn new<T>(py: Python, items: Vec<T>) -> PyResult<VecView>
+        where T: ToPyObject + Send + Sync + 'static
+    {
+        VecView::from_arc(py, Arc::new(items))
+    }
+
+    /// Creates a view over `items`, which may still be shared with Rust code.
+    pub fn from_arc<T>(py: Python, items: Arc<Vec<T>>) -> PyResult<VecView>
+        where T: ToPyObject + Send + Sync + 'static
+    {
+        let len = items.len();
+        VecView::create_instance(py, items, 0, 1, len)
+    }
+
+    /// Gets the item at position `index` of the view, which must be lower than the length.
+    fn item(&self, py: Python, index: usize) -> PyObject {
+        let position = *self.start(py) + index as isize * *self.step(py);
+        self.items(py).get_item(py, position as usize)
+    }
+
+    /// Gets the view over a slice of this view, sharing the same data.
+    fn slice(&self, py: Python, slice: &PyObject) -> PyResult<VecView> {
+        let (start, stop, step): (isize, isize, isize) =
+            slice.call_method(py, "indices", (*self.length(py),), None)?.extract(py)?;
+        let length = if step > 0 && stop > start {
+            (stop - start - 1) / step + 1
+        } else if step < 0 && start > stop {
+            (start - stop - 1) / -step + 1
+        } else {
+            0
+        };
+        VecView::create_instance(py, self.items(py).clone(),
+            *self.start(py) + start * *self.step(py),
+            *self.step(py) * step,
+            length as usize)
+    }
+}
+
+py_class!(pub class VecViewIterator |py| {
+    data view: VecView;
+    data position: Cell<usize>;
+
+    def __iter__(&self) -> PyResult<VecViewIterator> {
+        Ok(self.clone_ref(py))
+    }
+
+    def __next__(&self) -> PyResult<Option<PyObject>> {
+        let view = self.view(py);
+        let position = self.position(py).get();
+        if position < *view.length(py) {
+            self.position(py).set(position + 1);
+            Ok(Some(view.item(py, position)))
+        } else {
+            Ok(None)
+        }
+    }
+});
+
+/// A key or a value of a map wrapped by a `MapView`.
+#[doc(hidden)]
+pub trait MapEntry: Sync {
+    fn to_object(&self, py: Python) -> PyObject;
+}
+
+impl <T> MapEntry for T where T: ToPyObject + Sync {
+    fn to_object(&self, py: Python) -> PyObject {
+        self.to_py_object(py).into_object()
+    }
+}
+
+/// An iterator over the entries of a map wrapped by a `MapView`.
+#[doc(hidden)]
+pub type MapEntries<'a> = Box<Iterator<Item = (&'a MapEntry, &'a MapEntry)> + Send + 'a>;
+
+/// Type-erased access to a `HashMap` or a `BTreeMap`.
+#[doc(hidden)]
+pub trait MappingData: Send + Sync {
+    fn size(&self) -> usize;
+    fn get_value(&self, py: Python, key: &PyObject) -> Option<PyObject>;
+    fn entries<'a>(&'a self) -> MapEntries<'a>;
+}
+
+impl <K, V, S> MappingData for HashMap<K, V, S>
+    where K: for<'s> FromPyObject<'s> + ToPyObject + Eq + Hash + Send + Sync,
+          V: ToPyObject + Send + Sync,
+          S: BuildHasher + Send + Sync
+{
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    fn get_value(&self, py: Python, key: &PyObject) -> Option<PyObject> {
+        let key = key.extract::<K>(py).ok()?;
+        self.get(&key).map(|value| value.to_py_object(py).into_object())
+    }
+
+    fn entries<'a>(&'a self) -> MapEntries<'a> {
+        Box::new(self.iter().map(|(k, v)| (k as &MapEntry, v as &MapEntry)))
+    }
+}
+
+impl <K, V> MappingData for BTreeMap<K, V>
+    where K: for<'s> FromPyObject<'s> + ToPyObject + Ord + Send + Sync,
+          V: ToPyObject + Send + Sync
+{
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    fn get_value(&self, py: Python, key: &PyObject) -> Option<PyObject> {
+        let key = key.extract::<K>(py).ok()?;
+        self.get(&key).map(|value| value.to_py_object(py).into_object())
+    }
+
+    fn entries<'a>(&'a self) -> MapEntries<'a> {
+        Box::new(self.iter().map(|(k, v)| (k as &MapEntry, v as &MapEntry)))
+    }
+}
+
+py_class!(pub class MapView |py| {
+    data map: Arc<MappingData>;
+
+    def __len__(&self) -> PyResult<usize> {
+        Ok(self.map(py).size())
+    }
+
+    def __getitem__(&self, key: PyObject) -> PyResult<PyObject> {
+        match self.map(py).get_value(py, &key) {
+            Some(value) => Ok(value),
+            None => Err(PyErr::new::<exc::KeyError, _>(py, key)),
+        }
+    }
+
+    def __contains__(&self, key: PyObject) -> PyResult<bool> {
+        Ok(self.map(py).get_value(py, &key).is_some())
+    }
+
+    def __iter__(&self) -> PyResult<MapViewIterator> {
+        self.iterate(py, MapIterKind::Keys)
+    }
+
+    def get(&self, key: PyObject, default: Option<PyObject> = None) -> PyResult<PyObject> {
+        Ok(self.map(py).get_value(py, &key).or(default).unwrap_or_else(|| py.None()))
+    }
+
+    def keys(&self) -> PyResult<MapViewIterator> {
+        self.iterate(py, MapIterKind::Keys)
+    }
+
+    def values(&self) -> PyResult<MapViewIterator> {
+        self.iterate(py, MapIterKind::Values)
+    }
+
+    def items(&self) -> PyResult<MapViewIterator> {
+        self.iterate(py, MapIterKind::Items)
+    }
+
+    def __repr__(&self) -> PyResult<String> {
+        Ok(format!("<MapView of {} items>", self.map(py).size()))
+    }
+});
+
+impl MapView {
+    /// Creates a view over `map`, which may be a `HashMap` or a `BTreeMap`.
+    ///
+    /// Looking up a key from Python first converts it to the key type of the map
+    /// with `FromPyObject`; keys that cannot be converted are reported as missing.
+    pub fn new<M>(py: Python, map: M) -> PyResult<MapView>
+        where M: MappingData + 'static
+    {
+        MapView::from_arc(py, Arc::new(map))
+    }
+
+    /// Creates a view over `map`, which may still be shared with Rust code.
+    pub fn from_arc<M>(py: Python, map: Arc<M>) -> PyResult<MapView>
+        where M: MappingData + 'static
+    {
+        MapView::create_instance(py, map)
+    }
+
+    fn iterate(&self, py: Python, kind: MapIterKind) -> PyResult<MapViewIterator> {
+        let map = self.map(py).clone();
+        // The iterator borrows from the map, which is kept alive (and never mutated)
+        // by the `Arc` stored next to it in the `MapViewIterator`.
+        let entries = unsafe { mem::transmute::<MapEntries, MapEntries<'static>>(map.entries()) };
+        MapViewIterator::create_instance(py, RefCell::new(entries), kind, map)
+    }
+}
+
+/// The part of the map entries returned by a `MapViewIterator`.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+pub enum MapIterKind {
+    Keys,
+    Values,
+    Items,
+}
+
+py_class!(pub class MapViewIterator |py| {
+    // Declared before `_map` so that it is dropped first.
+    data entries: RefCell<MapEntries<'static>>;
+    data kind: MapIterKind;
+    data _map: Arc<MappingData>;
+
+    def __iter__(&self) -> PyResult<MapViewIterator> {
+        Ok(self.clone_ref(py))
+    }
+
+    def __next__(&self) -> PyResult<Option<PyObject>> {
+        let (key, value) = match self.entries(py).borrow_mut().next() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        Ok(Some(match *self.kind(py) {
+            MapIterKind::Keys => key.to_object(py),
+            MapIterKind::Values => value.to_object(py),
+            MapIterKind::Items => (key.to_object(py), value.to_object(py)).to_py_object(py).into_object(),
+        }))
+    }
+});
+
+/// The owner of the bytes exposed by a `BytesView`.
+trait ByteOwner: Send {
+    fn bytes(&self) -> &[u8];
+    fn owner_type(&self) -> TypeId;
+    fn into_any(self: Box<Self>) -> Box<Any>;
+}
+
+impl <B> ByteOwner for B where B: AsRef<[u8]> + Send + 'static {
+    fn bytes(&self) -> &[u8] {
+        self.as_ref()
+    }
+
+    fn owner_type(&self) -> TypeId {
+        TypeId::of::<B>()
+    }
+
+    fn into_any(self: Box<Self>) -> Box<Any> {
+        self
+    }
+}
+
+/// The layout of the instances of `BytesView`.
+#[repr(C)]
+struct BytesViewObject {
+    ob_base: ffi::PyObject,
+    // `None` once the view was revoked.
+    owner: Option<Box<ByteOwner>>,
+    // The number of buffers currently exported to Python.
+    exports: usize,
+}
+
+static mut BYTES_VIEW_TYPE: ffi::PyTypeObject = ffi::PyTypeObject {
+    tp_dealloc: Some(bytes_view_dealloc),
+    ..ffi::PyTypeObject_INIT
+};
+
+#[cfg(feature="python27-sys")]
+static mut BYTES_VIEW_BUFFER_PROCS: ffi::PyBufferProcs = ffi::PyBufferProcs {
+    bf_getbuffer: Some(bytes_view_getbuffer),
+    bf_releasebuffer: Some(bytes_view_releasebuffer),
+    ..ffi::PyBufferProcs_INIT
+};
+
+#[cfg(feature="python3-sys")]
+static mut BYTES_VIEW_BUFFER_PROCS: ffi::PyBufferProcs = ffi::PyBufferProcs {
+    bf_getbuffer: Some(bytes_view_getbuffer),
+    bf_releasebuffer: Some(bytes_view_releasebuffer),
+};
+
+#[cfg(feature="python27-sys")]
+const BYTES_VIEW_FLAGS: ::libc::c_long = slots::TPFLAGS_DEFAULT | ffi::Py_TPFLAGS_HAVE_NEWBUFFER;
+
+#[cfg(feature="python3-sys")]
+const BYTES_VIEW_FLAGS: ::libc::c_ulong = slots::TPFLAGS_DEFAULT;
+
+unsafe extern "C" fn bytes_view_dealloc(obj: *mut ffi::PyObject) {
+    let guard = AbortOnDrop("Cannot unwind out of tp_dealloc");
+    let py = Python::assume_gil_acquired();
+    ptr::drop_in_place(&mut (*(obj as *mut BytesViewObject)).owner);
+    PyObject::dealloc(py, obj);
+    mem::forget(guard);
+}
+
+// Not `unsafe`, for compatibility with the declaration of `getbufferproc` in python3-sys.
+extern "C" fn bytes_view_getbuffer(obj: *mut ffi::PyObject, view: *mut ffi::Py_buffer, flags: c_int) -> c_int {
+    unsafe { handle_callback("BytesView.getbuffer", UnitCallbackConverter, |py| {
+        let object = &mut *(obj as *mut BytesViewObject);
+        let (buf, len) = match object.owner {
+            Some(ref owner) => (owner.bytes().as_ptr(), owner.bytes().len()),
+            None => return Err(PyErr::new::<exc::BufferError, _>(py, "BytesView was revoked")),
+        };
+        if ffi::PyBuffer_FillInfo(view, obj, buf as *mut _, len as ffi::Py_ssize_t, 1, flags) != 0 {
+            return Err(PyErr::fetch(py));
+        }
+        object.exports += 1;
+        Ok(())
+    }) }
+}
+
+extern "C" fn bytes_view_releasebuffer(obj: *mut ffi::PyObject, _view: *mut ffi::Py_buffer) {
+    unsafe { (*(obj as *mut BytesViewObject)).exports -= 1; }
+}
+
+fn bytes_view_type(py: Python) -> PyResult<PyType> {
+    unsafe {
+        let ty = &mut BYTES_VIEW_TYPE as *mut ffi::PyTypeObject;
+        if !py_class::is_ready(py, &*ty) {
+            (*ty).init_ob_type(&mut ffi::PyType_Type);
+            (*ty).tp_name = b"BytesView\0".as_ptr() as *const c_char;
+            (*ty).tp_basicsize = mem::size_of::<BytesViewObject>() as ffi::Py_ssize_t;
+            (*ty).tp_flags = BYTES_VIEW_FLAGS;
+            (*ty).tp_as_buffer = &mut BYTES_VIEW_BUFFER_PROCS;
+            if ffi::PyType_Ready(ty) != 0 {
+                return Err(PyErr::fetch(py));
+            }
+        }
+        Ok(PyType::from_type_ptr(py, ty))
+    }
+}
+
+/// A read-only Python object exposing Rust-owned bytes with the buffer protocol.
+///
+/// The bytes can be used without copying them by `memoryview`, `numpy.frombuffer()`,
+/// `file.write()` and any other consumer of the buffer protocol;
+/// the Rust owner is kept alive as long as the `BytesView` or any buffer exported by it.
+///
+/// ```
+/// use cpython::{Python, PyDict};
+/// use cpython::views::BytesView;
+///
+/// let gil = Python::acquire_gil();
+/// let py = gil.python();
+/// let view = BytesView::new(py, b"hello world".to_vec()).unwrap();
+/// let locals = PyDict::new(py);
+/// locals.set_item(py, "view", &view).unwrap();
+/// py.run("assert memoryview(view)[6:] == b'world'", None, Some(&locals)).unwrap();
+///
+/// // Take back the data, e.g. to modify it.
+/// let mut data: Vec<u8> = view.revoke(py).unwrap();
+/// data.push(b'!');
+/// ```
+pub struct BytesView(PyObject);
+
+impl BytesView {
+    /// Creates a view over the bytes of `owner`, e.g. a `Vec<u8>`.
+    pub fn new<B>(py: Python, owner: B) -> PyResult<BytesView>
+        where B: AsRef<[u8]> + Send + 'static
+    {
+        let ty = bytes_view_type(py)?;
+        unsafe {
+            let obj = PyObject::alloc(py, &ty, ())?;
+            let object = obj.as_ptr() as *mut BytesViewObject;
+            ptr::write(&mut (*object).owner, Some(Box::new(owner) as Box<ByteOwner>));
+            (*object).exports = 0;
+            Ok(BytesView(obj))
+        }
+    }
+
+    fn object(&self) -> *mut BytesViewObject {
+        self.0.as_ptr() as *mut BytesViewObject
+    }
+
+    /// Gets the length of the bytes, or `None` if the view was revoked.
+    pub fn len(&self, _py: Python) -> Option<usize> {
+        unsafe { (*self.object()).owner.as_ref().map(|owner| owner.bytes().len()) }
+    }
+
+    /// Returns `true` if `revoke()` was called successfully.
+    pub fn is_revoked(&self, _py: Python) -> bool {
+        unsafe { (*self.object()).owner.is_none() }
+    }
+
+    /// Revokes the view and returns the owner of the bytes, so that it can be mutated.
+    ///
+    /// Once revoked, requesting a buffer from the view raises `BufferError`.
+    ///
+    /// Raises `BufferError` if buffers exported by the view are still in use (e.g. by a
+    /// `memoryview` that was not released) or if the view was already revoked,
+    /// and `TypeError` if the owner is not a `B`.
+    pub fn revoke<B: 'static>(&self, py: Python) -> PyResult<B> {
+        let object = unsafe { &mut *self.object() };
+        if object.exports != 0 {
+            return Err(PyErr::new::<exc::BufferError, _>(py, "BytesView has exported buffers"));
+        }
+        match object.owner {
+            Some(ref owner) if owner.owner_type() != TypeId::of::<B>() => {
+                return Err(PyErr::new::<exc::TypeError, _>(py, "BytesView has another owner type"));
+            }
+            Some(_) => {}
+            None => return Err(PyErr::new::<exc::BufferError, _>(py, "BytesView was revoked")),
+        }
+        match object.owner.take().unwrap().into_any().downcast::<B>() {
+            Ok(owner) => Ok(*owner),
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+py_impl_to_py_object_for_python_object!(BytesView);
+py_impl_from_py_object_for_python_object!(BytesView);
+
+impl PythonObject for BytesView {
+    #[inline]
+    fn as_object(&self) -> &PyObject {
+        &self.0
+    }
+
+    #[inline]
+    fn into_object(self) -> PyObject {
+        self.0
+    }
+
+    #[inline]
+    unsafe fn unchecked_downcast_from(obj: PyObject) -> BytesView {
+        BytesView(obj)
+    }
+
+    #[inline]
+    unsafe fn unchecked_downcast_borrow_from(obj: &PyObject) -> &BytesView {
+        mem::transmute::<&PyObject, &BytesView>(obj)
+    }
+}
+
+impl ::std::fmt::Debug for BytesView {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        ::std::fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl ::std::fmt::Display for BytesView {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        ::std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl PythonObjectWithCheckedDowncast for BytesView {
+    fn downcast_from<'p>(py: Python<'p>, obj: PyObject) -> Result<BytesView, PythonObjectDowncastError<'p>> {
+        if unsafe { ptr::eq(ffi::Py_TYPE(obj.as_ptr()), &BYTES_VIEW_TYPE) } {
+            Ok(BytesView(obj))
+        } else {
+            Err(PythonObjectDowncastError(py))
+        }
+    }
+
+    fn downcast_borrow_from<'a, 'p>(py: Python<'p>, obj: &'a PyObject) -> Result<&'a BytesView, PythonObjectDowncastError<'p>> {
+        if unsafe { ptr::eq(ffi::Py_TYPE(obj.as_ptr()), &BYTES_VIEW_TYPE) } {
+            Ok(unsafe { mem::transmute::<&PyObject, &BytesView>(obj) })
+        } else {
+            Err(PythonObjectDowncastError(py))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::Arc;
+    use python::Python;
+    use objects::PyDict;
+    use super::{VecView, MapView, BytesView};
+
+    #[test]
+    fn test_vec_view() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let data = Arc::new(vec!["a".to_owned(), "b".to_owned(), "c".to_owned(), "d".to_owned()]);
+        let d = PyDict::new(py);
+        d.set_item(py, "v", VecView::from_arc(py, data.clone()).unwrap()).unwrap();
+        py.run("assert len(v) == 4 and v[0] == 'a' and v[-1] == 'd'\n\
+                assert list(v) == ['a', 'b', 'c', 'd'] and 'c' in v and 'e' not in v\n\
+                assert list(v[1:]) == ['b', 'c', 'd'] and list(v[::-2]) == ['d', 'b']\n\
+                assert list(v[1:][::2]) == ['b', 'd'] and len(v[3:1]) == 0\n\
+                for i in (4, -5):\n\
+                \x20   try: v[i]\n\
+                \x20   except IndexError: pass\n\
+                \x20   else: assert False",
+            None, Some(&d)).unwrap();
+        assert_eq!(Arc::strong_count(&data), 2);
+    }
+
+    #[test]
+    fn test_map_view() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let mut hash_map = HashMap::new();
+        hash_map.insert("one".to_owned(), 1);
+        hash_map.insert("two".to_owned(), 2);
+        let mut btree_map = BTreeMap::new();
+        btree_map.insert(3u32, vec![1, 2, 3]);
+        btree_map.insert(1u32, vec![]);
+        let d = PyDict::new(py);
+        d.set_item(py, "h", MapView::new(py, hash_map).unwrap()).unwrap();
+        d.set_item(py, "b", MapView::new(py, btree_map).unwrap()).unwrap();
+        py.run("assert len(h) == 2 and h['one'] == 1 and 'two' in h and 'three' not in h and 1 not in h\n\
+                assert h.get('two') == 2 and h.get('three') is None and h.get(3, 0) == 0\n\
+                assert sorted(h) == ['one', 'two'] and sorted(h.values()) == [1, 2]\n\
+                assert dict(h.items()) == {'one': 1, 'two': 2}\n\
+                assert list(b.keys()) == [1, 3] and list(b.items()) == [(1, []), (3, [1, 2, 3])]\n\
+                try: b[2]\n\
+                except KeyError: pass\n\
+                else: assert False",
+            None, Some(&d)).unwrap();
+    }
+
+    #[test]
+    fn test_bytes_view() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let view = BytesView::new(py, vec![1u8, 2, 3]).unwrap();
+        let d = PyDict::new(py);
+        d.set_item(py, "v", &view).unwrap();
+        py.run("m = memoryview(v)\n\
+                assert m.readonly and m.tobytes() == b'\\x01\\x02\\x03'",
+            None, Some(&d)).unwrap();
+        if cfg!(feature="python3-sys") {
+            // Python 2's `bytes` is `str`, which doesn't read buffers.
+            py.run("assert bytes(v) == b'\\x01\\x02\\x03'", None, Some(&d)).unwrap();
+        }
+        assert!(view.revoke::<Vec<u8>>(py).is_err());
+        // Python 2 memoryviews have no `release()`; dropping the last reference releases the buffer.
+        py.run("del m", None, Some(&d)).unwrap();
+        assert!(view.revoke::<String>(py).is_err());
+        assert_eq!(view.revoke::<Vec<u8>>(py).unwrap(), vec![1, 2, 3]);
+        assert!(view.is_revoked(py));
+        assert!(py.run("memoryview(v)", None, Some(&d)).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright (c) 2018 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Weak references to Python objects.
+
+use std::marker::PhantomData;
+use ffi;
+use err::{self, PyResult};
+use objects::PyObject;
+use python::{Python, PythonObject, PythonObjectWithCheckedDowncast, PyClone, ToPythonPointer};
+
+/// A weak reference to a Python object of type `T`.
+///
+/// Like `PyObject`, a `PyWeak` can be stored in Rust data structures without holding the GIL,
+/// but it doesn't keep the object alive: e.g. a registry of Python callbacks can hold
+/// `PyWeak<PyObject>` and upgrade them when invoking the callbacks.
+///
+/// Only objects supporting weak references can be referenced (this includes functions,
+/// bound methods and instances of classes defined in Python, but not `int` or `str`).
+///
+/// ```
+/// use cpython::{Python, PyObject, PyWeak};
+///
+/// let gil = Python::acquire_gil();
+/// let py = gil.python();
+/// let callback: PyObject = py.eval("lambda: 42", None, None).unwrap();
+/// let weak = PyWeak::downgrade(py, &callback).unwrap();
+/// assert!(weak.upgrade(py).is_some());
+/// drop(callback);
+/// assert!(weak.upgrade(py).is_none());
+/// ```
+pub struct PyWeak<T> {
+    weakref: PyObject,
+    marker: PhantomData<T>,
+}
+
+impl <T> PyWeak<T> where T: PythonObjectWithCheckedDowncast {
+    /// Creates a weak reference to `obj`.
+    ///
+    /// Raises `TypeError` if the type of `obj` doesn't support weak references.
+    pub fn downgrade(py: Python, obj: &T) -> PyResult<PyWeak<T>> {
+        let weakref = unsafe {
+            err::result_from_owned_ptr(py, ffi::PyWeakref_NewRef(obj.as_object().as_ptr(), ::std::ptr::null_mut()))?
+        };
+        Ok(PyWeak { weakref, marker: PhantomData })
+    }
+
+    /// Gets the referenced object, or `None` if it was destroyed.
+    pub fn upgrade(&self, py: Python) -> Option<T> {
+        let obj = unsafe {
+            let ptr = ffi::PyWeakref_GetObject(self.weakref.as_ptr());
+            if ptr.is_null() || ptr == ffi::Py_None() {
+                return None;
+            }
+            PyObject::from_borrowed_ptr(py, ptr)
+        };
+        // The type of the object may have been changed by assigning to its `__class__`.
+        obj.cast_into::<T>(py).ok()
+    }
+
+    /// Returns `true` if the referenced object was not destroyed yet.
+    pub fn is_alive(&self, py: Python) -> bool {
+        self.upgrade(py).is_some()
+    }
+
+    /// Gets the underlying `weakref.ref` object.
+    pub fn as_object(&self) -> &PyObject {
+        &self.weakref
+    }
+}
+
+impl <T> PyClone for PyWeak<T> {
+    fn clone_ref(&self, py: Python) -> PyWeak<T> {
+        PyWeak { weakref: self.weakref.clone_ref(py), marker: PhantomData }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use python::Python;
+    use objects::{PyDict, PyObject};
+    use objectprotocol::ObjectProtocol;
+    use super::PyWeak;
+
+    #[test]
+    fn test_weak() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let d = PyDict::new(py);
+        py.run("class C: pass", None, Some(&d)).unwrap();
+        let obj = py.eval("C()", None, Some(&d)).unwrap();
+        let weak = PyWeak::downgrade(py, &d.get_item(py, "C").unwrap().call(py, ::NoArgs, None).unwrap()).unwrap();
+        assert!(!weak.is_alive(py));
+
+        let weak = PyWeak::downgrade(py, &obj).unwrap();
+        assert!(weak.upgrade(py).unwrap() == obj);
+        drop(obj);
+        assert!(weak.upgrade(py).is_none());
+
+        let number: PyObject = py.eval("12345", None, None).unwrap();
+        assert!(PyWeak::downgrade(py, &number).is_err());
+    }
+}
@@ -261,6 +261,67 @@ fn static_method_with_args() {
     py.run("assert C.method(1337) == '0x539'", None, Some(&d)).unwrap();
 }
 
+py_class!(class MethodSignatures |py| {
+    def method(&self, a: i32, b: i32 = 2) -> PyResult<i32> {
+        Ok(a * b)
+    }
+
+    @classmethod
+    def class_method(cls, a: i32) -> PyResult<i32> {
+        Ok(a)
+    }
+
+    @staticmethod
+    def static_method(a: i32, b: i32) -> PyResult<i32> {
+        Ok(a + b)
+    }
+});
+
+#[test]
+#[cfg(Py_3_4)]
+fn method_signatures() {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+
+    let d = PyDict::new(py);
+    d.set_item(py, "C", py.get_type::<MethodSignatures>()).unwrap();
+    py.run("import inspect", None, Some(&d)).unwrap();
+    py.run("assert str(inspect.signature(C.method)) == '(self, /, a, b=2)'", None, Some(&d)).unwrap();
+    py.run("assert str(inspect.signature(C.class_method)) == '(a)'", None, Some(&d)).unwrap();
+    py.run("assert str(inspect.signature(C.static_method)) == '(a, b)'", None, Some(&d)).unwrap();
+}
+
+py_class!(class Copyable |py| {
+    data values: Vec<i32>;
+    data shared: Arc<i32>;
+
+    def __copy__(&self) -> PyResult<Copyable> {
+        self.copy_data(py)
+    }
+
+    def __deepcopy__(&self, _memo: PyDict) -> PyResult<Copyable> {
+        self.copy_data(py)
+    }
+});
+
+#[test]
+fn copy() {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+
+    let obj = Copyable::create_instance(py, vec![1, 2], Arc::new(3)).unwrap();
+    let copy = obj.copy_data(py).unwrap();
+    assert!(copy.as_object() != obj.as_object());
+    assert_eq!(copy.values(py), &vec![1, 2]);
+    assert!(Arc::ptr_eq(copy.shared(py), obj.shared(py)));
+
+    let d = PyDict::new(py);
+    d.set_item(py, "obj", obj).unwrap();
+    py.run("import copy", None, Some(&d)).unwrap();
+    py.run("assert type(copy.copy(obj)) is type(obj) and copy.copy(obj) is not obj", None, Some(&d)).unwrap();
+    py.run("assert type(copy.deepcopy([obj])[0]) is type(obj)", None, Some(&d)).unwrap();
+}
+
 py_class!(class StaticData |py| {
     static VAL1 = 123;
     static VAL2 = py.None();
@@ -409,6 +470,92 @@ fn python3_string_methods() {
     py_assert!(py, obj, "bytes(obj) == b'bytes'");
 }
 
+#[test]
+#[cfg(Py_3_6)]
+fn format_string() {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+
+    let obj = StringMethods::create_instance(py).unwrap();
+    py_assert!(py, obj, "f'{obj:>3}' == 'format(>3)'");
+}
+
+py_class!(class PathLike |py| {
+    data path: String;
+
+    def __fspath__(&self) -> PyResult<String> {
+        Ok(self.path(py).clone())
+    }
+});
+
+#[test]
+#[cfg(Py_3_6)]
+fn fspath() {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+
+    let obj = PathLike::create_instance(py, "/tmp".to_owned()).unwrap();
+    py_run!(py, obj, "import os\n\
+assert os.fspath(obj) == '/tmp'\n\
+assert os.path.join(obj, 'file') == os.path.join('/tmp', 'file')\n\
+assert os.path.isdir(obj)");
+}
+
+py_class!(class SizeOf |py| {
+    data content: Vec<u8>;
+
+    def __sizeof__(&self) -> PyResult<usize> {
+        Ok(py_class::instance_size(py, self, self.content(py).capacity()))
+    }
+});
+
+#[test]
+fn sizeof_method() {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+
+    let obj = SizeOf::create_instance(py, Vec::with_capacity(1000)).unwrap();
+    let base_size = mem::size_of::<ffi::PyObject>() + mem::size_of::<Vec<u8>>();
+    let size = obj.__sizeof__(py).unwrap();
+    assert!(size >= base_size + 1000, "{}", size);
+    let d = PyDict::new(py);
+    d.set_item(py, "obj", obj).unwrap();
+    d.set_item(py, "size", size).unwrap();
+    py.run("import sys; assert sys.getsizeof(obj) >= size", None, Some(&d)).unwrap();
+}
+
+
+py_class!(class RichDisplay |py| {
+    def _repr_html_(&self) -> PyResult<&'static str> {
+        Ok("<b>html</b>")
+    }
+
+    def _repr_png_(&self) -> PyResult<Option<PyBytes>> {
+        Ok(None)
+    }
+
+    def _repr_mimebundle_(&self, include: Option<PyObject> = None, exclude: Option<PyObject> = None)
+        -> PyResult<display::MimeBundle>
+    {
+        Ok(display::MimeBundle::new()
+            .text(py, "text")
+            .markdown(py, "*markdown*")
+            .with_metadata(py, "text/markdown", 42))
+    }
+});
+
+#[test]
+fn rich_display() {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+
+    let obj = RichDisplay::create_instance(py).unwrap();
+    py_assert!(py, obj, "obj._repr_html_() == '<b>html</b>'");
+    py_assert!(py, obj, "obj._repr_png_() is None");
+    py_assert!(py, obj, "obj._repr_mimebundle_(include=None, exclude=None) == \
+        ({'text/plain': 'text', 'text/markdown': '*markdown*'}, {'text/markdown': 42})");
+}
+
 
 py_class!(class Comparisons |py| {
     data val: i32;
@@ -65,6 +65,73 @@ fn inline_two_args() {
     assert_eq!(obj.call(py, (6, 7), None).unwrap().extract::<i32>(py).unwrap(), 42);
 }
 
+#[test]
+#[cfg(Py_3_4)]
+fn signature() {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let obj = py_fn!(py, f(a: i32, b: i32 = 1, c: Option<String> = None) -> PyResult<i32> {
+        drop((py, c));
+        Ok(a * b)
+    });
+
+    let d = PyDict::new(py);
+    d.set_item(py, "f", obj).unwrap();
+    py.run("import inspect", None, Some(&d)).unwrap();
+    py.run("assert str(inspect.signature(f)) == '(a, b=1, c=None)'", None, Some(&d)).unwrap();
+    py.run("assert inspect.signature(f).bind(6, 7).arguments['b'] == 7", None, Some(&d)).unwrap();
+    py.run("assert f.__doc__ is None", None, Some(&d)).unwrap();
+    // `d` is used as the globals below, and older Python versions don't add `__builtins__` to them.
+    d.set_item(py, "__builtins__", py.builtins().unwrap()).unwrap();
+    py.run("import functools\n\
+@functools.wraps(f)\n\
+def g(*args, **kwargs): return f(*args, **kwargs)\n\
+assert g.__name__ == 'f'\n\
+assert str(inspect.signature(g)) == '(a, b=1, c=None)'\n\
+assert g(6, 7) == 42", Some(&d), None).unwrap();
+
+    let obj = py_fn!(py, f() -> PyResult<i32> { drop(py); Ok(0) });
+    d.set_item(py, "f", obj).unwrap();
+    py.run("assert str(inspect.signature(f)) == '()'", None, Some(&d)).unwrap();
+
+    let obj = py_fn!(py, f(a: i32 = -1, b: bool = false, c: &str = "x", d: f64 = 2.5) -> PyResult<i32> {
+        drop((py, b, c, d));
+        Ok(a)
+    });
+    d.set_item(py, "f", obj).unwrap();
+    py.run("assert str(inspect.signature(f)) == \"(a=-1, b=False, c='x', d=2.5)\"", None, Some(&d)).unwrap();
+    py.run("assert f() == -1", None, Some(&d)).unwrap();
+}
+
+const DEFAULT_B: i32 = 2;
+
+#[test]
+#[cfg(Py_3_4)]
+fn no_signature_with_non_literal_default() {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let d = PyDict::new(py);
+    py.run("import inspect", None, Some(&d)).unwrap();
+
+    let obj = py_fn!(py, f(a: i32, b: i32 = DEFAULT_B) -> PyResult<i32> { drop(py); Ok(a + b) });
+    d.set_item(py, "f", obj).unwrap();
+    py.run("assert f.__text_signature__ is None", None, Some(&d)).unwrap();
+    py.run("assert f(1) == 3", None, Some(&d)).unwrap();
+
+    let obj = py_fn!(py, f(a: i32, b: i32 = 1 + 1) -> PyResult<i32> { drop(py); Ok(a + b) });
+    d.set_item(py, "f", obj).unwrap();
+    py.run("assert f.__text_signature__ is None", None, Some(&d)).unwrap();
+    py.run("assert f(1) == 3", None, Some(&d)).unwrap();
+
+    let obj = py_fn!(py, f(a: i32, b: Vec<i32> = Vec::new()) -> PyResult<usize> {
+        drop(py);
+        Ok(a as usize + b.len())
+    });
+    d.set_item(py, "f", obj).unwrap();
+    py.run("assert f.__text_signature__ is None", None, Some(&d)).unwrap();
+    py.run("assert f(1, [1, 2]) == 3", None, Some(&d)).unwrap();
+}
+
 /* TODO: reimplement flexible sig support
 #[test]
 fn flexible_sig() {
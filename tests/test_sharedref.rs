@@ -0,0 +1,55 @@
+#![allow(dead_code, unused_variables)]
+
+#[macro_use] extern crate cpython;
+
+use cpython::*;
+use std::slice::Iter;
+
+py_class!(class Numbers |py| {
+    data numbers: PySharedRefCell<Vec<u32>>;
+
+    def push(&self, value: u32) -> PyResult<PyObject> {
+        self.shared_numbers(py).borrow_mut()?.push(value);
+        Ok(py.None())
+    }
+
+    def __len__(&self) -> PyResult<usize> {
+        Ok(self.shared_numbers(py).borrow().len())
+    }
+
+    def __iter__(&self) -> PyResult<NumbersIterator> {
+        let leaked = unsafe { self.shared_numbers(py).leak_immutable()? };
+        NumbersIterator::from_inner(py, unsafe { leaked.map(py, |v| v.iter()) })
+    }
+});
+
+py_shared_ref!(Numbers, Vec<u32>, numbers, shared_numbers);
+
+py_shared_iterator!(NumbersIterator, PyLeaked<Iter<'static, u32>>,
+                    |_py: Python, value: &u32| Ok(Some(*value)), u32);
+
+#[test]
+fn shared_iterator() {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let numbers = Numbers::create_instance(py, PySharedRefCell::new(vec![1, 2])).unwrap();
+    py_run!(py, numbers, "\
+numbers.push(3)
+assert list(numbers) == [1, 2, 3]
+it = iter(numbers)
+assert next(it) == 1
+assert list(it) == [2, 3]
+numbers.push(4)  # the exhausted iterator released the data
+assert len(numbers) == 4
+it = iter(numbers)
+assert next(it) == 1
+numbers.push(5)  # mutation invalidates the iterator
+try:
+    next(it)
+except RuntimeError:
+    pass
+else:
+    assert False, 'iteration after mutation'
+assert list(numbers) == [1, 2, 3, 4, 5]
+");
+}